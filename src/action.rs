@@ -0,0 +1,108 @@
+use crate::feed::Item;
+use url::Url;
+
+// Many feed items are calls to action: RMS asks readers to sign a petition or contact their
+// representatives. The linked pages are campaign forms rather than articles, and the forms are
+// removed by the article filters, so these links are not fetched. They are shown as a
+// call-to-action card with RMS's comment instead.
+
+/// Title prefixes RMS uses for calls to action (lowercase).
+const TITLE_PREFIXES: &[&str] = &["urgent:", "urgent "];
+
+/// Campaign sites. Subdomains match too.
+const ACTION_DOMAINS: &[&str] = &[
+    "actionnetwork.org",
+    "avaaz.org",
+    "change.org",
+    "credoaction.com",
+    "democracy.io",
+    "moveon.org",
+    "resistbot.io",
+    "rootsaction.org",
+    "sumofus.org",
+];
+
+/// The first label of campaign subdomains, e.g. act.eff.org.
+const ACTION_SUBDOMAINS: &[&str] = &["act", "action", "actions", "petition", "petitions", "sign"];
+
+/// Path segments of campaign pages, e.g. https://www.aclu.org/take-action/...
+const ACTION_PATHS: &[&str] = &[
+    "action",
+    "actions",
+    "campaigns",
+    "petition",
+    "petitions",
+    "sign",
+    "take-action",
+    "takeaction",
+];
+
+/// Returns the call-to-action links of the item. If the title marks the item as a call to action
+/// but none of the links look like campaign pages, all links are returned.
+pub fn action_links(item: &Item) -> Vec<String> {
+    let links = item
+        .links
+        .iter()
+        .filter(|link| is_action_link(link))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let title = item.title.trim().to_lowercase();
+    if links.is_empty() && TITLE_PREFIXES.iter().any(|p| title.starts_with(p)) {
+        item.links.clone()
+    } else {
+        links
+    }
+}
+
+fn is_action_link(link: &str) -> bool {
+    let url = match Url::parse(link) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+
+    let action_domain = ACTION_DOMAINS
+        .iter()
+        .any(|d| host == *d || host.ends_with(&format!(".{}", d)));
+    let action_subdomain = host
+        .split('.')
+        .next()
+        .is_some_and(|label| ACTION_SUBDOMAINS.contains(&label));
+    let action_path = url
+        .path_segments()
+        .is_some_and(|mut segments| segments.any(|s| ACTION_PATHS.contains(&&*s.to_lowercase())));
+
+    action_domain || action_subdomain || action_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, links: &[&str]) -> Item {
+        Item {
+            title: title.to_string(),
+            date: None,
+            description: "".to_string(),
+            links: links.iter().map(|l| l.to_string()).collect(),
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn finds_action_links() {
+        let article = "https://www.theguardian.com/world/2020/nov/22/action-on-climate";
+        let petition = "https://www.change.org/p/stop-this";
+        let links = |title: &str, links: &[&str]| action_links(&item(title, links));
+
+        assert_eq!(links("Stop this", &[article, petition]), [petition]);
+        assert_eq!(links("EFF", &["https://act.eff.org/action/x"]).len(), 1);
+        assert_eq!(
+            links("ACLU", &["https://www.aclu.org/take-action/x"]).len(),
+            1
+        );
+        assert!(links("Climate", &[article]).is_empty());
+        assert_eq!(links("Urgent: Call your senators", &[article]), [article]);
+    }
+}
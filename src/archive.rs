@@ -0,0 +1,195 @@
+use crate::export::{DigestItem, FetchStatus, SCHEMA_VERSION};
+use crate::feed::Item;
+use crate::resolve::ResolvedItem;
+use crate::util;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use chrono::Local;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A feed item and its articles as stored in the archive.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivedItem {
+    /// The JSON export schema version of `item`. Items archived with an older schema version
+    /// can still be read.
+    pub schema_version: u32,
+    /// The archive id of the item, see `item_id`.
+    pub id: String,
+    /// When the item was last written to the archive (RFC 3339).
+    pub archived: String,
+    pub item: DigestItem,
+}
+
+/// A directory containing every resolved feed item, stored as one JSON file per item.
+pub struct Archive {
+    dir: PathBuf,
+}
+
+impl Archive {
+    /// Opens the archive in the data directory, creating it if needed.
+    pub fn open() -> AnyResult<Self> {
+        Self::at(util::data_dir()?.join("archive"))
+    }
+
+    pub fn at(dir: PathBuf) -> AnyResult<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create archive directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stores the resolved items and returns the ids of the items that were written. Items
+    /// without a date can not be identified and are not stored. An already archived item is only
+    /// replaced if at least as many of its articles could be fetched this time.
+    pub fn store(&self, resolved: &[ResolvedItem]) -> AnyResult<Vec<String>> {
+        let mut written = Vec::new();
+
+        for resolved in resolved {
+            let id = match item_id(&resolved.item) {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let item = DigestItem::new(resolved);
+            if let Ok(existing) = self.get(&id) {
+                if ok_count(&existing.item) > ok_count(&item) {
+                    continue;
+                }
+            }
+
+            let archived = ArchivedItem {
+                schema_version: SCHEMA_VERSION,
+                id: id.clone(),
+                archived: Local::now().to_rfc3339(),
+                item,
+            };
+            fs::write(self.path(&id), serde_json::to_string_pretty(&archived)?)?;
+            written.push(id);
+        }
+
+        Ok(written)
+    }
+
+    pub fn get(&self, id: &str) -> AnyResult<ArchivedItem> {
+        let json = fs::read_to_string(self.path(id))
+            .with_context(|| format!("item {} is not archived", id))?;
+        let item: ArchivedItem = serde_json::from_str(&json)?;
+        if item.schema_version > SCHEMA_VERSION {
+            bail!("item {} has unsupported schema version", id);
+        }
+        Ok(item)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.path(id).is_file()
+    }
+
+    /// Returns the ids of all archived items. Other files in the archive directory (such as the
+    /// search index) are ignored.
+    pub fn ids(&self) -> AnyResult<Vec<String>> {
+        lazy_static! {
+            static ref ITEM_FILE: Regex = Regex::new(r"^(\d{8}-[0-9a-f]{16})\.json$").unwrap();
+        }
+
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            if let Some(cap) = name.to_str().and_then(|name| ITEM_FILE.captures(name)) {
+                ids.push(cap[1].to_string());
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+/// Returns an id that identifies the feed item across runs, or None if the item has no date.
+/// The id starts with the item date so that ids sort chronologically.
+pub fn item_id(item: &Item) -> Option<String> {
+    let date = item.date?;
+    let key = format!("{}\n{}", date.to_rfc3339(), item.title);
+    Some(format!(
+        "{}-{:016x}",
+        date.format("%Y%m%d"),
+        util::stable_hash(&key)
+    ))
+}
+
+fn ok_count(item: &DigestItem) -> usize {
+    item.articles
+        .iter()
+        .filter(|a| a.status == FetchStatus::Ok)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::Article;
+    use crate::resolve::LinkError;
+    use chrono::TimeZone;
+
+    #[test]
+    fn stores_and_replaces_items() {
+        let dir = std::env::temp_dir().join(format!("tds-archive-test-{}", std::process::id()));
+        let archive = Archive::at(dir.clone()).unwrap();
+
+        let ok = resolved_item(true);
+        let ids = archive.store(&[ok]).unwrap();
+        assert_eq!(ids.len(), 1);
+        fs::write(dir.join("index.json"), "{}").unwrap();
+        assert_eq!(archive.ids().unwrap(), ids);
+        assert_eq!(archive.get(&ids[0]).unwrap().item.title, "Title");
+
+        // A run where the article failed must not replace the archived article.
+        let failed = resolved_item(false);
+        assert!(archive.store(&[failed]).unwrap().is_empty());
+        let stored = archive.get(&ids[0]).unwrap().item.into_resolved();
+        assert_eq!(stored.articles.len(), 1);
+        assert_eq!(stored.articles[0].text, "text");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn resolved_item(ok: bool) -> ResolvedItem {
+        let url = "https://example.com/".to_string();
+        let (articles, errors) = if ok {
+            let article = Article {
+                url: url.clone(),
+                html: "<p>text</p>".to_string(),
+                text: "text".to_string(),
+                ..Default::default()
+            };
+            (vec![article], vec![])
+        } else {
+            let error = LinkError {
+                url: url.clone(),
+                error: "failed".to_string(),
+            };
+            (vec![], vec![error])
+        };
+
+        ResolvedItem {
+            item: Item {
+                title: "Title".to_string(),
+                date: Some(Local.ymd(2020, 11, 22).and_hms(10, 0, 0)),
+                description: "".to_string(),
+                links: vec![url],
+                tags: vec![],
+            },
+            articles,
+            errors,
+            actions: vec![],
+        }
+    }
+}
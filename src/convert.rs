@@ -1,79 +1,756 @@
 use crate::extract::Article;
+use crate::feed::Item;
+use crate::filter;
+use crate::glossary::Glossary;
+use crate::options::{Opts, Theme};
 use crate::resolve::ResolvedItem;
+use crate::tags;
+use crate::template::{Template, Values};
+use crate::util;
+use anyhow::Context;
+use anyhow::Result as AnyResult;
+use chrono::{Duration, Local};
+use html5ever::{local_name, namespace_url, ns, QualName};
+use kuchiki::traits::TendrilSink;
+use kuchiki::{Attribute, ExpandedName, NodeRef};
+use std::fs;
+use url::Url;
 
-pub fn html(items: &[ResolvedItem]) -> String {
-    let items_html = items
+/// Articles published this long before RMS posted them are marked as old.
+const STALE_AFTER_DAYS: i64 = 30;
+
+lazy_static! {
+    static ref DEFAULT_TEMPLATE: Template =
+        Template::parse(include_str!("../resources/templates/default.html"))
+            .expect("invalid built-in template");
+    static ref PRINT_TEMPLATE: Template =
+        Template::parse(include_str!("../resources/templates/print.html"))
+            .expect("invalid built-in template");
+}
+
+const BASE_CSS: &str = include_str!("../resources/classless.css");
+
+/// Links to these sites (and their subdomains) in RMS's comments explain the terms he uses, so
+/// they are shown as notes after the comment.
+const GLOSSARY_DOMAINS: &[&str] = &["gnu.org", "stallman.org"];
+
+/// The group of items without tags when the output is grouped by tag.
+const UNTAGGED_GROUP: &str = "Other";
+
+/// The template and CSS used to render the HTML output.
+pub struct Layout {
+    /// A user template, or None for the built-in template.
+    template: Option<Template>,
+    css: String,
+    /// Print mode: the print template is used and links are numbered and listed as footnotes
+    /// since they can't be followed on paper.
+    print: bool,
+    /// The articles were not fetched, so the links are listed instead.
+    links_only: bool,
+    /// Each article ends with a list of the links in it.
+    references: bool,
+    /// The tag order if the items are grouped by tag.
+    groups: Option<Vec<String>>,
+    /// The definitions shown for glossary links.
+    glossary: Glossary,
+}
+
+impl Layout {
+    /// Loads the user template and CSS files, if given. The CSS file replaces the theme CSS.
+    pub fn load(opts: &Opts) -> AnyResult<Layout> {
+        let template = match opts.template.as_deref() {
+            Some(path) => {
+                let template = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read template {}", path.display()))?;
+                let template = Template::parse(&template)
+                    .with_context(|| format!("invalid template {}", path.display()))?;
+                Some(template)
+            }
+            None => None,
+        };
+        let css = match opts.css.as_deref() {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("failed to read CSS file {}", path.display()))?,
+            None => theme_css(opts.theme),
+        };
+        Ok(Layout {
+            template,
+            css,
+            print: opts.print,
+            links_only: opts.links_only,
+            references: opts.references,
+            groups: if opts.group_by_tag {
+                Some(opts.tagger.names())
+            } else {
+                None
+            },
+            glossary: opts.glossary.clone(),
+        })
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            template: None,
+            css: theme_css(Theme::Default),
+            print: false,
+            links_only: false,
+            references: false,
+            groups: None,
+            glossary: Glossary::default(),
+        }
+    }
+}
+
+/// The built-in themes are applied on top of classless.css.
+fn theme_css(theme: Theme) -> String {
+    let theme_css = match theme {
+        Theme::Default => "",
+        Theme::Dark => include_str!("../resources/themes/dark.css"),
+        Theme::HighContrast => include_str!("../resources/themes/high-contrast.css"),
+        Theme::Print => include_str!("../resources/themes/print.css"),
+    };
+    format!("{}\n{}", BASE_CSS, theme_css)
+}
+
+pub fn html(items: &[ResolvedItem], layout: &Layout) -> String {
+    let builtin: &Template = if layout.print {
+        &PRINT_TEMPLATE
+    } else {
+        &DEFAULT_TEMPLATE
+    };
+    let template = layout.template.as_ref().unwrap_or(builtin);
+
+    let values = items
         .iter()
-        .map(item_to_html)
-        .collect::<Vec<_>>()
-        .join("<br/><hr><br/><br/><br/>");
-
-    format!(
-        "
-    <!DOCTYPE html>
-    <html>
-        <head>
-        <style>{}</style>
-        </head>
-        <body>{}</body>
-    </html>",
-        include_str!("../resources/classless.css"),
-        items_html
+        .enumerate()
+        .map(|(i, item)| item_values(item, i, layout))
+        .collect::<Vec<_>>();
+    // Without grouping, all items are in a single group without a tag.
+    let groups = match layout.groups.as_deref() {
+        Some(order) => tag_groups(items, &values, order),
+        None => vec![Values::new().list("items", values.clone())],
+    };
+
+    template.render(
+        &Values::new()
+            .html("css", layout.css.as_str())
+            .text("generated", Local::now().format("%B %-d, %Y").to_string())
+            .bool("grouped", layout.groups.is_some())
+            .bool("links_only", layout.links_only)
+            .list("groups", groups)
+            .list("items", values),
     )
 }
 
-fn item_to_html(resolved: &ResolvedItem) -> String {
-    let articles_html = resolved
-        .articles
+/// Groups the items by their first tag, in the given tag order. Tags that are not in the order
+/// (from archived items tagged with another dictionary) come after, then the untagged items.
+fn tag_groups(items: &[ResolvedItem], values: &[Values], order: &[String]) -> Vec<Values> {
+    let mut groups = order
         .iter()
-        .map(article_to_html)
-        .collect::<Vec<_>>()
-        .join("<p><hr></p>");
+        .map(|tag| (tag.clone(), Vec::new()))
+        .collect::<Vec<_>>();
+    let mut untagged = Vec::new();
 
-    format!(
-        "<p><strong>RMS says:</strong></p><blockquote>{}</blockquote>{}",
-        resolved.item.description, articles_html
-    )
+    for (resolved, values) in items.iter().zip(values) {
+        let group = match resolved.item.tags.first() {
+            Some(tag) => match groups.iter().position(|(t, _)| t == tag) {
+                Some(i) => &mut groups[i].1,
+                None => {
+                    groups.push((tag.clone(), Vec::new()));
+                    &mut groups.last_mut().expect("group was added").1
+                }
+            },
+            None => &mut untagged,
+        };
+        group.push(values.clone());
+    }
+    groups.push((UNTAGGED_GROUP.to_string(), untagged));
+
+    groups
+        .into_iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(tag, items)| {
+            Values::new()
+                .text("anchor", tags::anchor(&tag))
+                .text("count", items.len().to_string())
+                .text("tag", tag)
+                .list("items", items)
+        })
+        .collect()
 }
 
-fn article_to_html(art: &Article) -> String {
-    let link = &art.url;
-    let title = art.title.as_deref().unwrap_or(link);
-    let authors = art.authors.join(", ");
-    let publishing_date = art.publishing_date.as_deref().unwrap_or_default();
-    let html = &art.html;
-
-    return format!(
-        "<div>
-        <h1>{}</h1>
-        <a href=\"{}\">{}</a>
-        {}
-        {}
-    </div>
-    ",
-        title,
-        link,
-        link,
-        authors_date_elem(&authors, &publishing_date),
-        html
-    );
-
-    fn authors_date_elem(authors: &str, date: &str) -> String {
-        let float = if authors.is_empty() || date.is_empty() {
-            "left"
+fn item_values(resolved: &ResolvedItem, index: usize, layout: &Layout) -> Values {
+    let item = &resolved.item;
+    let anchors = (1..=resolved.articles.len())
+        .map(|i| format!("article-{}-{}", index + 1, i))
+        .collect::<Vec<_>>();
+    let mut description_links = DescriptionLinks {
+        articles: resolved
+            .articles
+            .iter()
+            .map(|art| art.url.as_str())
+            .zip(anchors.iter().map(String::as_str))
+            .collect(),
+        note_prefix: format!("note-{}-", index + 1),
+        notes: Vec::new(),
+        glossary: &layout.glossary,
+    };
+    let mut footnotes = Footnotes::default();
+    let mut footnoted = |html: String| {
+        if layout.print {
+            footnotes.add_links(&html)
         } else {
-            "right"
-        };
-
-        format!(
-            r#"
-        <h5>
-            <span style="float: left;">{}</span>
-            <span style="float: {}; margin-right: 10%">{}</span>
-        </h5>
-        <br/>
-        "#,
-            authors, float, date
+            html
+        }
+    };
+
+    let date = item
+        .date
+        .map(|date| date.format("%B %-d, %Y").to_string())
+        .unwrap_or_default();
+    let links = item
+        .links
+        .iter()
+        .map(|link| Values::new().text("url", link.as_str()))
+        .collect();
+    let actions = resolved
+        .actions
+        .iter()
+        .map(|link| Values::new().text("url", link.as_str()))
+        .collect();
+    let article_links = item
+        .links
+        .iter()
+        .filter(|link| !resolved.actions.contains(link))
+        .map(|link| Values::new().text("url", link.as_str()))
+        .collect();
+    let errors = resolved
+        .errors
+        .iter()
+        .map(|e| {
+            Values::new()
+                .text("url", e.url.as_str())
+                .text("error", e.error.as_str())
+        })
+        .collect();
+
+    // Links can't be followed on paper, so in print mode all links are footnoted instead.
+    let description = if layout.print {
+        footnoted(description_html(item))
+    } else {
+        description_links.rewrite(&description_html(item))
+    };
+    let articles = resolved
+        .articles
+        .iter()
+        .zip(&anchors)
+        .map(|(art, anchor)| {
+            article_values(art, item, layout.references)
+                .text("anchor", anchor.as_str())
+                .html("html", footnoted(art.html.clone()))
+        })
+        .collect();
+    let tags = item
+        .tags
+        .iter()
+        .map(|tag| {
+            Values::new()
+                .text("tag", tag.as_str())
+                .text("anchor", tags::anchor(tag))
+        })
+        .collect();
+
+    Values::new()
+        .text("title", item.title.as_str())
+        .text("date", date)
+        .html("description", description)
+        .list("links", links)
+        .list("article_links", article_links)
+        .list("tags", tags)
+        .list("actions", actions)
+        .list("articles", articles)
+        .list("errors", errors)
+        .list("notes", description_links.values())
+        .list("footnotes", footnotes.values(&layout.glossary))
+}
+
+/// RMS's comment is HTML so it is sanitized rather than escaped.
+fn description_html(item: &Item) -> String {
+    let node = kuchiki::parse_html().one(item.description.as_str());
+    filter::sanitize(&node);
+    body_html(&node)
+}
+
+fn body_html(node: &NodeRef) -> String {
+    node.select_first("body")
+        .map(|body| {
+            body.as_node()
+                .children()
+                .map(|child| child.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn article_values(art: &Article, item: &Item, references: bool) -> Values {
+    let publishing_date = art
+        .publishing_date
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let references = if references {
+        art.links
+            .iter()
+            .map(|link| {
+                Values::new()
+                    .text("url", link.url.as_str())
+                    .text("text", link.text.as_str())
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Values::new()
+        .text("title", art.title.as_deref().unwrap_or(&art.url))
+        .text("url", art.url.as_str())
+        .text("authors", art.authors.join(", "))
+        .text("date", publishing_date)
+        .bool("old", is_stale(art, item))
+        .text("description", art.description.clone().unwrap_or_default())
+        .text("lead_image", art.lead_image.clone().unwrap_or_default())
+        .text("publisher", art.publisher.clone().unwrap_or_default())
+        .text("section", art.section.clone().unwrap_or_default())
+        .list("references", references)
+}
+
+/// The links of a feed item in print mode. Every link is followed by its footnote number.
+#[derive(Default)]
+struct Footnotes {
+    urls: Vec<String>,
+}
+
+impl Footnotes {
+    /// Adds a footnote reference after each link in the HTML and returns the new HTML. Links to
+    /// the same URL share a footnote.
+    fn add_links(&mut self, html: &str) -> String {
+        let node = kuchiki::parse_html().one(html);
+        let links = match node.select("a[href]") {
+            Ok(links) => links.collect::<Vec<_>>(),
+            Err(_) => return html.to_string(),
+        };
+
+        for link in links {
+            let url = match link.attributes.borrow().get("href") {
+                Some(url) if util::is_http(url) => url.to_string(),
+                _ => continue,
+            };
+            let number = match self.urls.iter().position(|u| *u == url) {
+                Some(i) => i + 1,
+                None => {
+                    self.urls.push(url);
+                    self.urls.len()
+                }
+            };
+
+            let sup =
+                NodeRef::new_element(QualName::new(None, ns!(html), local_name!("sup")), None);
+            sup.append(NodeRef::new_text(format!("[{}]", number)));
+            link.as_node().insert_after(sup);
+        }
+
+        body_html(&node)
+    }
+
+    fn values(self, glossary: &Glossary) -> Vec<Values> {
+        self.urls
+            .into_iter()
+            .enumerate()
+            .map(|(i, url)| {
+                let definition = glossary.get(&url).map(|entry| entry.definition.clone());
+                Values::new()
+                    .text("number", (i + 1).to_string())
+                    .text("definition", definition.unwrap_or_default())
+                    .text("url", url)
+            })
+            .collect()
+    }
+}
+
+/// Rewrites the links in RMS's comment so that the digest can be read without leaving the page:
+/// links to fetched articles point to the article further down and links to glossary pages become
+/// notes with the definition from the glossary, if it has one. Other links are left as they are.
+struct DescriptionLinks<'a> {
+    /// The URL and anchor of every fetched article.
+    articles: Vec<(&'a str, &'a str)>,
+    /// The anchors of the notes are the prefix and the note number.
+    note_prefix: String,
+    /// The URL and link text of every note.
+    notes: Vec<(String, String)>,
+    glossary: &'a Glossary,
+}
+
+impl DescriptionLinks<'_> {
+    fn rewrite(&mut self, html: &str) -> String {
+        let node = kuchiki::parse_html().one(html);
+        let links = match node.select("a[href]") {
+            Ok(links) => links.collect::<Vec<_>>(),
+            Err(_) => return html.to_string(),
+        };
+
+        for link in links {
+            let url = match link.attributes.borrow().get("href") {
+                Some(url) if util::is_http(url) => url.to_string(),
+                _ => continue,
+            };
+
+            if let Some(anchor) = self.article_anchor(&url) {
+                link.attributes
+                    .borrow_mut()
+                    .insert("href", format!("#{}", anchor));
+            } else if is_glossary_link(&url) || self.glossary.get(&url).is_some() {
+                let definition = self.glossary.get(&url).map(|e| e.definition.as_str());
+                let number = match self.notes.iter().position(|(u, _)| *u == url) {
+                    Some(i) => i + 1,
+                    None => {
+                        self.notes
+                            .push((url, link.text_contents().trim().to_string()));
+                        self.notes.len()
+                    }
+                };
+                // The term stays in the text, followed by a link to its note.
+                let node = link.as_node();
+                for child in node.children() {
+                    node.insert_before(child);
+                }
+                node.insert_after(note_reference(&self.note_prefix, number, definition));
+                node.detach();
+            }
+        }
+
+        body_html(&node)
+    }
+
+    /// Returns the anchor of the article with the URL. The URL of an article is normalized, so the
+    /// link is normalized too before comparing.
+    fn article_anchor(&self, url: &str) -> Option<&str> {
+        let normalized = Url::parse(url).map(|url| url.to_string()).ok();
+        self.articles
+            .iter()
+            .find(|(article, _)| *article == url || normalized.as_deref() == Some(*article))
+            .map(|(_, anchor)| *anchor)
+    }
+
+    fn values(self) -> Vec<Values> {
+        let prefix = self.note_prefix;
+        let glossary = self.glossary;
+        self.notes
+            .into_iter()
+            .enumerate()
+            .map(|(i, (url, text))| {
+                let entry = glossary.get(&url);
+                Values::new()
+                    .text("number", (i + 1).to_string())
+                    .text("anchor", format!("{}{}", prefix, i + 1))
+                    .text("term", entry.map_or(text.as_str(), |e| e.term.as_str()))
+                    .text("definition", entry.map_or("", |e| e.definition.as_str()))
+                    .text("url", url.as_str())
+                    .text("text", text.as_str())
+            })
+            .collect()
+    }
+}
+
+fn is_glossary_link(url: &str) -> bool {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+        .unwrap_or_default();
+    GLOSSARY_DOMAINS
+        .iter()
+        .any(|d| host == *d || host.ends_with(&format!(".{}", d)))
+}
+
+/// A superscript link to the note with the number, e.g. <sup><a href="#note-1-2">[2]</a></sup>.
+/// The definition is shown as the tooltip of the link.
+fn note_reference(prefix: &str, number: usize, definition: Option<&str>) -> NodeRef {
+    let attribute = |name, value: String| {
+        (
+            ExpandedName::new(ns!(), name),
+            Attribute {
+                prefix: None,
+                value,
+            },
         )
+    };
+    let mut attributes = vec![attribute(
+        local_name!("href"),
+        format!("#{}{}", prefix, number),
+    )];
+    if let Some(definition) = definition {
+        attributes.push(attribute(local_name!("title"), definition.to_string()));
+    }
+    let link = NodeRef::new_element(QualName::new(None, ns!(html), local_name!("a")), attributes);
+    link.append(NodeRef::new_text(format!("[{}]", number)));
+    let sup = NodeRef::new_element(QualName::new(None, ns!(html), local_name!("sup")), None);
+    sup.append(link);
+    sup
+}
+
+fn is_stale(art: &Article, item: &Item) -> bool {
+    let published = art.publishing_date.as_ref().and_then(|d| d.date());
+    match (published, item.date) {
+        (Some(published), Some(posted)) => {
+            posted.naive_local().date() - published > Duration::days(STALE_AFTER_DAYS)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolve::{self, LinkError};
+
+    #[test]
+    fn escapes_article_text() {
+        let article = Article {
+            url: "https://example.com/?a=\"><script>".to_string(),
+            title: Some("<i>Title</i> & \"more\"".to_string()),
+            authors: vec!["<b>A</b>".to_string()],
+            html: "<p>body</p>".to_string(),
+            ..Default::default()
+        };
+        let item = Item {
+            title: "".to_string(),
+            date: None,
+            description: "<p onclick=\"alert(1)\">RMS <a href=\"javascript:x\">says</a></p>"
+                .to_string(),
+            links: vec![],
+            tags: vec![],
+        };
+
+        let resolved = ResolvedItem {
+            item,
+            articles: vec![article],
+            errors: vec![],
+            actions: vec![],
+        };
+        let html = html(&[resolved], &Layout::default());
+
+        assert!(html.contains("<h1>&lt;i&gt;Title&lt;/i&gt; &amp; \"more\"</h1>"));
+        assert!(html.contains("href=\"https://example.com/?a=&quot;&gt;&lt;script&gt;\""));
+        assert!(html.contains("&lt;b&gt;A&lt;/b&gt;"));
+        assert!(html.contains("<p>body</p>"));
+        assert!(html.contains("<blockquote><p>RMS <a>says</a></p></blockquote>"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn renders_user_template() {
+        let layout = Layout {
+            template: Some(
+                Template::parse(
+                    "<style>{{css}}</style>{{#each items}}<h2>{{title}}</h2>\
+                    {{#each errors}}<a href=\"{{url}}\">{{error}}</a>{{/each}}{{/each}}",
+                )
+                .unwrap(),
+            ),
+            css: theme_css(Theme::Dark),
+            print: false,
+            links_only: false,
+            references: false,
+            groups: None,
+            glossary: Glossary::default(),
+        };
+        let resolved = ResolvedItem {
+            item: Item {
+                title: "Item & title".to_string(),
+                date: None,
+                description: "".to_string(),
+                links: vec!["https://example.com/".to_string()],
+                tags: vec![],
+            },
+            articles: vec![],
+            errors: vec![LinkError {
+                url: "https://example.com/".to_string(),
+                error: "timeout".to_string(),
+            }],
+            actions: vec![],
+        };
+
+        let html = html(&[resolved], &layout);
+
+        assert!(html.contains("Dark theme"));
+        assert!(html.ends_with(
+            "</style><h2>Item &amp; title</h2><a href=\"https://example.com/\">timeout</a>"
+        ));
+    }
+
+    #[test]
+    fn footnotes_links_in_print_mode() {
+        let article = Article {
+            url: "https://example.com/article".to_string(),
+            html: "<p><a href=\"https://example.com/a\">a</a> <a href=\"#top\">top</a> \
+                <a href=\"https://example.com/b\">b</a></p>"
+                .to_string(),
+            ..Default::default()
+        };
+        let resolved = ResolvedItem {
+            item: Item {
+                title: "".to_string(),
+                date: None,
+                description: "<p><a href=\"https://example.com/b\">link</a></p>".to_string(),
+                links: vec![],
+                tags: vec![],
+            },
+            articles: vec![article],
+            errors: vec![],
+            actions: vec![],
+        };
+        let layout = Layout {
+            template: None,
+            css: theme_css(Theme::Print),
+            print: true,
+            links_only: false,
+            references: false,
+            groups: None,
+            glossary: Glossary::default(),
+        };
+
+        let html = html(&[resolved], &layout);
+
+        assert!(html.contains("<a href=\"https://example.com/b\">link</a><sup>[1]</sup>"));
+        assert!(html.contains("<a href=\"https://example.com/a\">a</a><sup>[2]</sup>"));
+        assert!(html.contains("<a href=\"#top\">top</a> "));
+        assert!(html.contains("<a href=\"https://example.com/b\">b</a><sup>[1]</sup>"));
+        assert!(
+            html.contains("<ol><li>https://example.com/b</li><li>https://example.com/a</li></ol>")
+        );
+        assert!(html.contains("class=\"item\""));
+    }
+
+    #[test]
+    fn groups_items_by_first_tag() {
+        let item = |title: &str, tags: &[&str]| ResolvedItem {
+            item: Item {
+                title: title.to_string(),
+                date: None,
+                description: "".to_string(),
+                links: vec![],
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+            },
+            articles: vec![],
+            errors: vec![],
+            actions: vec![],
+        };
+        let items = [
+            item("a", &["patents", "Covid"]),
+            item("b", &[]),
+            item("c", &["Covid"]),
+            item("d", &["archived"]),
+        ];
+        let layout = Layout {
+            template: Some(
+                Template::parse(
+                    "{{#each groups}}[{{tag}} {{count}}:{{#each items}} {{title}}{{/each}}]\
+                    {{/each}}",
+                )
+                .unwrap(),
+            ),
+            css: String::new(),
+            print: false,
+            links_only: false,
+            references: false,
+            groups: Some(vec![
+                "urgent".to_string(),
+                "Covid".to_string(),
+                "patents".to_string(),
+            ]),
+            glossary: Glossary::default(),
+        };
+
+        assert_eq!(
+            html(&items, &layout),
+            "[Covid 1: c][patents 1: a][archived 1: d][Other 1: b]"
+        );
+    }
+
+    #[test]
+    fn lists_links_without_articles() {
+        let items = resolve::unresolved_items(vec![
+            Item {
+                title: "Title".to_string(),
+                date: None,
+                description: "<a href=\"https://example.com/a\">article</a>".to_string(),
+                links: vec!["https://example.com/a".to_string()],
+                tags: vec![],
+            },
+            Item {
+                title: "Stop this".to_string(),
+                date: None,
+                description: "Read this and sign the petition.".to_string(),
+                links: vec![
+                    "https://example.com/b".to_string(),
+                    "https://act.example.org/petition".to_string(),
+                ],
+                tags: vec![],
+            },
+        ]);
+        let layout = Layout {
+            links_only: true,
+            ..Layout::default()
+        };
+
+        let output = html(&items, &layout);
+        assert!(
+            output.contains("<li><a href=\"https://example.com/a\">https://example.com/a</a></li>")
+        );
+        // The article link of a call to action is listed, the campaign link is on the card.
+        assert!(
+            output.contains("<li><a href=\"https://example.com/b\">https://example.com/b</a></li>")
+        );
+        assert!(!output.contains("<li><a href=\"https://act.example.org/petition\">"));
+        assert!(output.contains("Take action: <a href=\"https://act.example.org/petition\">"));
+        assert!(!html(&items, &Layout::default()).contains("<li>"));
+    }
+
+    #[test]
+    fn links_description_to_articles_and_notes() {
+        let resolved = ResolvedItem {
+            item: Item {
+                title: "Title".to_string(),
+                date: None,
+                description: "<a href=\"https://example.com\">Article</a> on \
+                    <a href=\"https://www.gnu.org/philosophy/not-ipr.html\">intellectual \
+                    property</a>, see <a href=\"https://other.example.com/\">this</a>."
+                    .to_string(),
+                links: vec![
+                    "https://example.com".to_string(),
+                    "https://other.example.com/".to_string(),
+                ],
+                tags: vec![],
+            },
+            articles: vec![Article {
+                url: "https://example.com/".to_string(),
+                ..Default::default()
+            }],
+            errors: vec![],
+            actions: vec![],
+        };
+
+        let html = html(&[resolved], &Layout::default());
+
+        assert!(html.contains(
+            "<a href=\"#article-1-1\">Article</a> on intellectual property\
+            <sup><a href=\"#note-1-1\" title=\"A misleading term"
+        ));
+        assert!(html.contains("</sup>, see <a href=\"https://other.example.com/\">this</a>."));
+        assert!(html.contains(
+            "<li id=\"note-1-1\"><strong>Intellectual property</strong>: A misleading term"
+        ));
+        assert!(html.contains("<div id=\"article-1-1\">"));
     }
 }
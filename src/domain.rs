@@ -0,0 +1,122 @@
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use regex::{Regex, RegexBuilder};
+use url::Url;
+
+/// Links to these domains are never fetched: they are RMS's own pages.
+const DEFAULT_DENY: &[&str] = &["stallman.org"];
+
+/// Decides which article links are fetched by the domain of the link.
+///
+/// A pattern like `example.com` matches the domain and all its subdomains. A `*` matches any part
+/// of a domain name, so `*.example.com` only matches the subdomains and `example.*` matches any
+/// top level domain.
+#[derive(Debug)]
+pub struct DomainFilter {
+    /// If not empty, only links to these domains are fetched.
+    allow: Vec<Pattern>,
+    /// Links to these domains are never fetched, even if they are allowed.
+    deny: Vec<Pattern>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl DomainFilter {
+    /// Creates a filter with the given patterns. The default deny patterns are always included.
+    pub fn new(allow: &[String], deny: &[String]) -> AnyResult<Self> {
+        let deny = DEFAULT_DENY
+            .iter()
+            .map(|p| p.to_string())
+            .chain(deny.iter().cloned());
+        Ok(DomainFilter {
+            allow: allow
+                .iter()
+                .cloned()
+                .map(Pattern::new)
+                .collect::<AnyResult<_>>()?,
+            deny: deny.map(Pattern::new).collect::<AnyResult<_>>()?,
+        })
+    }
+
+    /// Returns why the link is skipped, or None if it should be fetched.
+    pub fn skip_reason(&self, link: &str) -> Option<String> {
+        let url = match Url::parse(link) {
+            Ok(url) => url,
+            Err(err) => return Some(format!("invalid URL ({})", err)),
+        };
+        let host = match url.host_str() {
+            Some(host) => host.trim_end_matches('.').to_lowercase(),
+            None => return Some("the URL has no domain".to_string()),
+        };
+
+        if let Some(pattern) = self.deny.iter().find(|p| p.regex.is_match(&host)) {
+            Some(format!("{} is denied by `{}`", host, pattern.pattern))
+        } else if !self.allow.is_empty() && !self.allow.iter().any(|p| p.regex.is_match(&host)) {
+            Some(format!("{} is not in the allowed domains", host))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for DomainFilter {
+    fn default() -> Self {
+        DomainFilter::new(&[], &[]).expect("the default patterns are valid")
+    }
+}
+
+impl Pattern {
+    fn new(pattern: String) -> AnyResult<Self> {
+        let valid = !pattern.is_empty()
+            && pattern
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '.' || c == '-' || c == '*');
+        if !valid {
+            bail!("invalid domain pattern `{}`", pattern);
+        }
+
+        let domain = regex::escape(&pattern).replace(r"\*", "[^.]*");
+        // Patterns without wildcards also match the subdomains.
+        let regex = if pattern.contains('*') {
+            format!("^{}$", domain)
+        } else {
+            format!(r"^(.+\.)?{}$", domain)
+        };
+        let regex = RegexBuilder::new(&regex).case_insensitive(true).build()?;
+        Ok(Pattern { pattern, regex })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_domains_and_wildcards() {
+        let patterns = |p: &[&str]| p.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let filter = DomainFilter::new(&[], &patterns(&["*.gnu.org", "twitter.*"])).unwrap();
+        let skipped = |link| filter.skip_reason(link).is_some();
+
+        assert!(skipped("https://stallman.org/archives/2020-sep-dec.html"));
+        assert!(skipped("http://www.stallman.org/"));
+        assert!(skipped("https://Stallman.ORG/"));
+        assert!(skipped("https://www.gnu.org/philosophy/free-sw.html"));
+        assert!(!skipped("https://gnu.org/"));
+        assert!(skipped("https://twitter.com/rms"));
+        assert!(!skipped("https://notstallman.org/"));
+        assert!(!skipped("https://www.theguardian.com/world"));
+        assert_eq!(
+            filter.skip_reason("https://stallman.org/"),
+            Some("stallman.org is denied by `stallman.org`".to_string())
+        );
+
+        let allow = DomainFilter::new(&patterns(&["theguardian.com"]), &[]).unwrap();
+        assert_eq!(allow.skip_reason("https://www.theguardian.com/world"), None);
+        assert!(allow.skip_reason("https://example.com/").is_some());
+        assert!(DomainFilter::new(&patterns(&["https://example.com"]), &[]).is_err());
+    }
+}
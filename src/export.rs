@@ -0,0 +1,268 @@
+use crate::extract::{Article, PublishingDate};
+use crate::feed::Item;
+use crate::links::Link;
+use crate::resolve::{LinkError, ResolvedItem};
+use anyhow::Result as AnyResult;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The version of the JSON export schema. This is bumped whenever a field is removed or renamed
+/// or the meaning of a field changes. New fields may be added without bumping the version.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The root object of the JSON export. See the README for a description of the schema.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Digest {
+    pub schema_version: u32,
+    /// When the digest was generated (RFC 3339).
+    pub generated: String,
+    pub items: Vec<DigestItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestItem {
+    /// The feed item title.
+    pub title: String,
+    /// The date the item was added to the feed (RFC 3339).
+    pub date: Option<String>,
+    /// RMS's comment on the linked articles (HTML).
+    pub description: String,
+    /// The article links found in the description.
+    pub links: Vec<String>,
+    /// The topics of the item. Empty in items archived before tagging was added.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// One entry per link that is not a call-to-action link, in the same order as `links`.
+    pub articles: Vec<DigestArticle>,
+    /// The call-to-action links, such as petitions. These are not fetched.
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestArticle {
+    pub url: String,
+    pub status: FetchStatus,
+    /// Why the article could not be fetched. Only set if `status` is `error`.
+    pub error: Option<String>,
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    /// The publishing date in ISO 8601 format (e.g. 2020-11-22 or 2020-11-22T17:24:42).
+    pub publishing_date: Option<String>,
+    /// The publishing date as found in the article.
+    pub publishing_date_raw: Option<String>,
+    pub description: Option<String>,
+    /// The URL of the lead image.
+    pub lead_image: Option<String>,
+    pub publisher: Option<String>,
+    pub section: Option<String>,
+    /// The extracted article body (HTML).
+    pub html: Option<String>,
+    /// The extracted article body as plain text.
+    pub text: Option<String>,
+    /// The outbound links in the article body.
+    #[serde(default)]
+    pub links: Vec<Link>,
+    /// The extractor that found the article body, see the README.
+    pub extractor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchStatus {
+    Ok,
+    Error,
+}
+
+impl Digest {
+    pub fn new(items: &[ResolvedItem]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            generated: Local::now().to_rfc3339(),
+            items: items.iter().map(DigestItem::new).collect(),
+        }
+    }
+}
+
+impl DigestItem {
+    pub fn new(resolved: &ResolvedItem) -> Self {
+        let item = &resolved.item;
+
+        let mut articles = resolved
+            .articles
+            .iter()
+            .map(DigestArticle::ok)
+            .chain(resolved.errors.iter().map(DigestArticle::error))
+            .collect::<Vec<_>>();
+
+        // Articles and errors are stored separately so restore the order of the item links.
+        articles.sort_by_key(|a| link_position(&item.links, &a.url));
+
+        Self {
+            title: item.title.clone(),
+            date: item.date.map(|d| d.to_rfc3339()),
+            description: item.description.clone(),
+            links: item.links.clone(),
+            tags: item.tags.clone(),
+            articles,
+            actions: resolved.actions.clone(),
+        }
+    }
+
+    /// Converts the exported item back to a resolved item, e.g. to render archived items.
+    pub fn into_resolved(self) -> ResolvedItem {
+        let mut articles = Vec::new();
+        let mut errors = Vec::new();
+
+        for article in self.articles {
+            match article.status {
+                FetchStatus::Ok => articles.push(Article {
+                    url: article.url,
+                    title: article.title,
+                    authors: article.authors,
+                    // Version 1 only had a single date field in whatever format the site used.
+                    publishing_date: article
+                        .publishing_date_raw
+                        .or(article.publishing_date)
+                        .map(|raw| PublishingDate::parse(&raw)),
+                    description: article.description,
+                    lead_image: article.lead_image,
+                    publisher: article.publisher,
+                    section: article.section,
+                    html: article.html.unwrap_or_default(),
+                    text: article.text.unwrap_or_default(),
+                    links: article.links,
+                    extractor: article.extractor.unwrap_or_default(),
+                }),
+                FetchStatus::Error => errors.push(LinkError {
+                    url: article.url,
+                    error: article.error.unwrap_or_default(),
+                }),
+            }
+        }
+
+        ResolvedItem {
+            item: Item {
+                title: self.title,
+                date: self
+                    .date
+                    .and_then(|d| DateTime::parse_from_rfc3339(&d).ok())
+                    .map(DateTime::from),
+                description: self.description,
+                links: self.links,
+                tags: self.tags,
+            },
+            articles,
+            errors,
+            actions: self.actions,
+        }
+    }
+}
+
+impl DigestArticle {
+    fn ok(article: &Article) -> Self {
+        Self {
+            url: article.url.clone(),
+            status: FetchStatus::Ok,
+            error: None,
+            title: article.title.clone(),
+            authors: article.authors.clone(),
+            publishing_date: article
+                .publishing_date
+                .as_ref()
+                .and_then(PublishingDate::normalized),
+            publishing_date_raw: article.publishing_date.as_ref().map(|d| d.raw.clone()),
+            description: article.description.clone(),
+            lead_image: article.lead_image.clone(),
+            publisher: article.publisher.clone(),
+            section: article.section.clone(),
+            html: Some(article.html.clone()),
+            text: Some(article.text.clone()),
+            links: article.links.clone(),
+            extractor: Some(article.extractor.clone()),
+        }
+    }
+
+    fn error(error: &LinkError) -> Self {
+        Self {
+            url: error.url.clone(),
+            status: FetchStatus::Error,
+            error: Some(error.error.clone()),
+            title: None,
+            authors: Vec::new(),
+            publishing_date: None,
+            publishing_date_raw: None,
+            description: None,
+            lead_image: None,
+            publisher: None,
+            section: None,
+            html: None,
+            text: None,
+            links: Vec::new(),
+            extractor: None,
+        }
+    }
+}
+
+// The article URL is normalized when it is parsed so it may not be identical to the link.
+fn link_position(links: &[String], url: &str) -> usize {
+    let normalize = |s: &str| Url::parse(s).map(Url::into_string).unwrap_or_default();
+    let url = normalize(url);
+    links
+        .iter()
+        .position(|link| normalize(link) == url)
+        .unwrap_or(links.len())
+}
+
+/// Serializes the resolved items as a pretty printed JSON document.
+pub fn json(items: &[ResolvedItem]) -> AnyResult<String> {
+    Ok(serde_json::to_string_pretty(&Digest::new(items))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_articles_in_link_order() {
+        let resolved = ResolvedItem {
+            item: Item {
+                title: "Title".to_string(),
+                date: None,
+                description: "<p>desc</p>".to_string(),
+                links: vec![
+                    "https://a.example.com".to_string(),
+                    "https://b.example.com/".to_string(),
+                ],
+                tags: vec!["patents".to_string()],
+            },
+            articles: vec![Article {
+                url: "https://b.example.com/".to_string(),
+                title: Some("B".to_string()),
+                publishing_date: Some(PublishingDate::parse("Nov 22, 2020")),
+                html: "<p>b</p>".to_string(),
+                text: "b".to_string(),
+                ..Default::default()
+            }],
+            errors: vec![LinkError {
+                url: "https://a.example.com".to_string(),
+                error: "timed out".to_string(),
+            }],
+            actions: vec![],
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&json(&[resolved]).unwrap()).unwrap();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["items"][0]["tags"][0], "patents");
+        let articles = &json["items"][0]["articles"];
+        assert_eq!(articles[0]["status"], "error");
+        assert_eq!(articles[0]["error"], "timed out");
+        assert_eq!(articles[1]["status"], "ok");
+        assert_eq!(articles[1]["title"], "B");
+        assert_eq!(articles[1]["text"], "b");
+        assert_eq!(articles[1]["publishing_date"], "2020-11-22");
+        assert_eq!(articles[1]["publishing_date_raw"], "Nov 22, 2020");
+    }
+}
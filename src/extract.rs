@@ -1,34 +1,97 @@
 use crate::filter;
 use crate::filter::{remove_all, remove_all_class};
+use crate::links::{self, Link};
+use crate::metadata;
+use crate::readability;
+use crate::util;
 use anyhow::Result as AnyResult;
 use anyhow::*;
+use chrono::NaiveDate;
 use extrablatt::date::Date;
 use extrablatt::select::document::Document;
-use extrablatt::select::node::Node;
 use extrablatt::select::predicate::{Attr, Class, Name, Predicate};
 use extrablatt::{Extractor, Language};
 use html5ever::{local_name, namespace_url, ns, QualName};
 use kuchiki::traits::*;
 use kuchiki::NodeRef;
 use regex::Regex;
-use std::collections::HashMap;
+use std::fmt;
 use std::str;
 use url::Url;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Article {
     pub url: String,
     pub title: Option<String>,
     pub authors: Vec<String>,
-    pub publishing_date: Option<String>,
+    pub publishing_date: Option<PublishingDate>,
+    /// A short summary of the article as given by the site.
+    pub description: Option<String>,
+    /// The URL of the article's lead image.
+    pub lead_image: Option<String>,
+    /// The name of the site or organization that published the article.
+    pub publisher: Option<String>,
+    /// The section of the site the article was published in, e.g. "World".
+    pub section: Option<String>,
     pub html: String,
+    /// The article body as plain text, with paragraphs separated by blank lines.
+    pub text: String,
+    /// The outbound links in the article body, without navigational and ad links.
+    pub links: Vec<Link>,
+    /// The extractor that found the article content: the site domain for sites with a custom
+    /// extractor, otherwise "extrablatt" or "readability".
+    pub extractor: String,
+}
+
+/// An article publishing date as found in the page together with its parsed value.
+#[derive(Debug, Clone)]
+pub struct PublishingDate {
+    /// The date in the format used by the site.
+    pub raw: String,
+    /// The parsed date, None if the date is in an unknown format.
+    pub parsed: Option<Date>,
+}
+
+impl PublishingDate {
+    pub fn parse(raw: &str) -> Self {
+        Self {
+            raw: raw.to_string(),
+            parsed: util::parse_publishing_date(raw),
+        }
+    }
+
+    /// The day the article was published.
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self.parsed.as_ref()? {
+            Date::Date(d) => Some(*d),
+            Date::DateTime(d) => Some(d.date()),
+        }
+    }
+
+    /// The parsed date in ISO 8601 format, e.g. 2020-11-22 or 2020-11-22T17:24:42.
+    pub fn normalized(&self) -> Option<String> {
+        match self.parsed.as_ref()? {
+            Date::Date(d) => Some(d.format("%Y-%m-%d").to_string()),
+            Date::DateTime(d) => Some(d.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        }
+    }
+}
+
+/// Formats the date like "November 22, 2020", or as found if the date could not be parsed.
+impl fmt::Display for PublishingDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.date() {
+            Some(date) => write!(f, "{}", date.format("%B %-d, %Y")),
+            None => f.write_str(&self.raw),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct ExtractionParts {
     title: Option<String>,
     authors: Vec<String>,
-    publishing_date: Option<String>,
+    publishing_date: Option<PublishingDate>,
     article_node: Option<NodeRef>,
 }
 
@@ -53,27 +116,51 @@ pub struct ArticleExtractor<E: Extractor> {
     pub extractor: E,
     pub url: Url,
     pub doc: Document,
-    pub print_warnings: bool,
 }
 
 impl<E: Extractor> ArticleExtractor<E> {
     pub fn extract(&self) -> AnyResult<Article> {
         let parts = self.extract_article_parts();
 
-        let html = self.article_html(parts.article_node)?;
+        let (node, extractor) = match parts.article_node {
+            Some(node) => (node, site_domain(&self.url).unwrap_or_default()),
+            None => {
+                let (node, extractor) = self
+                    .default_article_node_with_extractor()
+                    .ok_or_else(|| anyhow!("failed to extract article html"))?;
+                (node, extractor.to_string())
+            }
+        };
+        filter::do_global_filtering(&node, &self.url);
+        let links = links::article_links(&node, &self.url);
+        let text = node_to_text(&node);
+        let html = node_to_html(node);
 
         let url = self.url.to_string();
 
-        let title = parts.title.or_else(|| self.default_title());
+        // Metadata published by the site is more reliable than the extrablatt heuristics, but
+        // custom extractors know best.
+        let metadata = metadata::extract(&self.doc);
+
+        let title = parts
+            .title
+            .or(metadata.title)
+            .or_else(|| self.default_title());
 
         let publishing_date = parts
             .publishing_date
+            .or(metadata
+                .publishing_date
+                .as_deref()
+                .map(PublishingDate::parse))
             .or_else(|| self.default_publishing_date());
 
-        let authors = if parts.authors.is_empty() {
-            self.default_authors()
-        } else {
+        let authors = if !parts.authors.is_empty() {
             parts.authors
+        } else if !metadata.authors.is_empty() {
+            metadata.authors
+        } else {
+            self.default_authors()
         };
 
         Ok(Article {
@@ -81,30 +168,30 @@ impl<E: Extractor> ArticleExtractor<E> {
             title,
             authors,
             publishing_date,
+            description: metadata.description,
+            lead_image: metadata.image,
+            publisher: metadata.publisher,
+            section: metadata.section,
             html,
+            text,
+            links,
+            extractor,
         })
     }
 
-    fn article_html(&self, article_node: Option<NodeRef>) -> AnyResult<String> {
-        let node = article_node
-            .or_else(|| self.default_article_node())
-            .ok_or_else(|| anyhow!("failed to extract article html"))?;
-
-        filter::do_global_filtering(&node, &self.url);
-
-        Ok(node_to_html(node))
-    }
-
     fn default_title(&self) -> Option<String> {
         self.extractor.title(&self.doc).map(|t| t.to_string())
     }
 
-    fn default_publishing_date(&self) -> Option<String> {
+    fn default_publishing_date(&self) -> Option<PublishingDate> {
         self.extractor
             .publishing_date(&self.doc, Some(&self.url))
-            .map(|date| match date.published {
-                Date::Date(d) => d.to_string(),
-                Date::DateTime(d) => d.date().to_string(),
+            .map(|date| PublishingDate {
+                raw: match &date.published {
+                    Date::Date(d) => d.to_string(),
+                    Date::DateTime(d) => d.to_string(),
+                },
+                parsed: Some(date.published),
             })
     }
 
@@ -117,49 +204,27 @@ impl<E: Extractor> ArticleExtractor<E> {
     }
 
     /// Try to extract the article node with extrablatt or if that fails try to extract it
-    /// using the score_divs function.
+    /// using the readability content scoring.
     fn default_article_node(&self) -> Option<NodeRef> {
-        self.extractor
-            .article_node(&self.doc, Language::English)
-            .map(|n| select_to_kuchiki(&n))
-            .or_else(|| self.score_divs().get(0).map(|n| select_to_kuchiki(&n.0)))
+        self.default_article_node_with_extractor()
+            .map(|(node, _)| node)
     }
 
-    /// Finds all <p> tags in the document and then finds their first <div>
-    /// ancestor (if there is one). These divs are then scored based on how much text their
-    /// descendant <p> tags contains, and sorted in descending order.
-    fn score_divs(&self) -> Vec<(Node, usize)> {
-        let ptags = self.doc.find(Name("p"));
-        let mut text_count: HashMap<usize, (Node, usize)> = HashMap::new();
-
-        for p in ptags {
-            if let Some(div) = div_ancestor(p) {
-                let mut entry = text_count.entry(div.index()).or_insert((div, 0));
-                (*entry).1 += p.text().len();
-            }
-        }
-
-        let mut ret = text_count.values().cloned().collect::<Vec<_>>();
-        ret.sort_unstable_by_key(|v| -(v.1 as i64));
-        return ret;
-
-        fn div_ancestor(mut node: Node) -> Option<Node> {
-            while let Some(parent) = node.parent() {
-                if parent.name() == Some("div") {
-                    return Some(parent);
-                }
-                node = parent;
-            }
-            None
-        }
+    /// Like `default_article_node` but also returns the name of the extractor that was used.
+    fn default_article_node_with_extractor(&self) -> Option<(NodeRef, &'static str)> {
+        self.extractor
+            .article_node(&self.doc, Language::English)
+            .map(|n| (select_to_kuchiki(&n), "extrablatt"))
+            .or_else(|| {
+                let root = self.doc.find(Name("html")).next()?;
+                readability::article_node(&select_to_kuchiki(&root))
+                    .map(|node| (node, "readability"))
+            })
     }
 
     fn warn<T>(&self, option: Option<T>, msg: &str) -> Option<T> {
-        if option.is_none() && self.print_warnings {
-            println!(
-                "WARNING: ({}) {} - falling back on default extractor",
-                self.url, msg
-            );
+        if option.is_none() {
+            warn!("({}) {} - falling back on default extractor", self.url, msg);
         }
         option
     }
@@ -331,7 +396,7 @@ fn site_domain(url: &Url) -> Option<String> {
     url.domain().and_then(|d| {
         let mut split = d.split('.').collect::<Vec<_>>();
         split.reverse();
-        let tld = split.get(0)?;
+        let tld = split.first()?;
         let site = split.get(1)?;
         Some(format!("{}.{}", site, tld))
     })
@@ -350,6 +415,76 @@ fn node_to_html(node: NodeRef) -> String {
     html
 }
 
+/// Returns the text content of the node. Block level elements are separated by blank lines and
+/// whitespace inside each block is collapsed.
+fn node_to_text(node: &NodeRef) -> String {
+    const BLOCKS: &[&str] = &[
+        "address",
+        "article",
+        "blockquote",
+        "br",
+        "dd",
+        "div",
+        "dl",
+        "dt",
+        "figcaption",
+        "figure",
+        "footer",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "header",
+        "hr",
+        "li",
+        "main",
+        "ol",
+        "p",
+        "pre",
+        "section",
+        "table",
+        "td",
+        "th",
+        "tr",
+        "ul",
+    ];
+
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+    collect(node, &mut blocks, &mut current);
+    flush(&mut blocks, &mut current);
+    return blocks.join("\n\n");
+
+    fn collect(node: &NodeRef, blocks: &mut Vec<String>, current: &mut String) {
+        for child in node.children() {
+            if let Some(text) = child.as_text() {
+                current.push_str(&text.borrow());
+            } else if let Some(elem) = child.as_element() {
+                let is_block = BLOCKS.contains(&&*elem.name.local);
+                if is_block {
+                    flush(blocks, current);
+                }
+                collect(&child, blocks, current);
+                if is_block {
+                    flush(blocks, current);
+                }
+            } else {
+                collect(&child, blocks, current);
+            }
+        }
+    }
+
+    fn flush(blocks: &mut Vec<String>, current: &mut String) {
+        let block = current.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+        current.clear();
+    }
+}
+
 fn select_to_kuchiki(node: &extrablatt::select::node::Node) -> NodeRef {
     kuchiki::parse_html().one(node.html())
 }
@@ -357,7 +492,6 @@ fn select_to_kuchiki(node: &extrablatt::select::node::Node) -> NodeRef {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use extrablatt::DefaultExtractor;
 
     #[test]
     fn converts_node_to_html_correctly() {
@@ -366,30 +500,14 @@ mod tests {
     }
 
     #[test]
-    fn scores_divs_correctly() {
-        let html = "<html>
-            <head />
-            <body>
-                <div><p><strong>strong text</strong></p></div>
-                <div>
-                    <p>Some text.</p>
-                    <p>And some more.</p>
-                </div>
-                <div>no p</div>
-            </body>
-        </html>";
-
-        let extractor = ArticleExtractor {
-            extractor: DefaultExtractor::default(),
-            url: Url::parse("http://www.example.com").unwrap(),
-            doc: Document::from(html),
-            print_warnings: false,
-        };
-
-        let score = extractor.score_divs();
-
-        assert_eq!(score.len(), 2);
-        assert_eq!(score[0].1, 24);
-        assert_eq!(score[1].1, 11);
+    fn converts_node_to_text_correctly() {
+        let node = kuchiki::parse_html().one(
+            "<div><h1>Title</h1><p>First   <em>paragraph</em>\n text.</p>\
+            <ul><li>one</li><li>two</li></ul></div>",
+        );
+        assert_eq!(
+            "Title\n\nFirst paragraph text.\n\none\n\ntwo",
+            node_to_text(&node)
+        );
     }
 }
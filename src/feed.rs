@@ -1,8 +1,11 @@
+use crate::domain::DomainFilter;
+use crate::options::{FetchType, Opts};
 use crate::util;
 use anyhow::Result as AnyResult;
 use anyhow::*;
 use chrono::{DateTime, Local};
 use kuchiki::traits::TendrilSink;
+use regex::{Regex, RegexBuilder};
 use rss::Channel;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -10,7 +13,7 @@ use std::io::Read;
 use std::time::Duration;
 
 /// A stallman.org news feed item.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Item {
     /// The news item title.
     pub title: String,
@@ -23,24 +26,165 @@ pub struct Item {
     /// May also be plain file names (without http:// or https://) in which case a test file
     /// with that name will be used.
     pub links: Vec<String>,
+    /// The topics of the item, set by `filter_items`.
+    pub tags: Vec<String>,
+}
+
+impl Item {
+    /// The title and the text of the description, including the link texts.
+    pub fn text(&self) -> String {
+        let description = kuchiki::parse_html().one(self.description.as_str());
+        format!("{}\n{}", self.title, description.text_contents())
+    }
+}
+
+/// The cache validators of the last feed response, used to only download the feed again if it
+/// has changed.
+#[derive(Debug, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Selects items by the text of their title and description, including the link texts, and by
+/// their tags. The patterns and tags are case insensitive.
+#[derive(Debug, Default)]
+pub struct ItemFilter {
+    matches: Vec<Regex>,
+    excludes: Vec<Regex>,
+    /// Whether an item must match all the match patterns instead of any of them.
+    match_all: bool,
+    /// If not empty, items must have one of these tags.
+    tags: Vec<String>,
+}
+
+impl ItemFilter {
+    pub fn new(
+        matches: &[String],
+        excludes: &[String],
+        match_all: bool,
+        tags: &[String],
+    ) -> AnyResult<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    RegexBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .with_context(|| format!("invalid pattern `{}`", pattern))
+                })
+                .collect::<AnyResult<Vec<_>>>()
+        };
+        Ok(ItemFilter {
+            matches: compile(matches)?,
+            excludes: compile(excludes)?,
+            match_all,
+            tags: tags.iter().map(|tag| tag.to_lowercase()).collect(),
+        })
+    }
+
+    pub fn is_match(&self, item: &Item) -> bool {
+        let has_tag = self.tags.is_empty()
+            || item
+                .tags
+                .iter()
+                .any(|tag| self.tags.contains(&tag.to_lowercase()));
+        if !has_tag {
+            return false;
+        }
+        if self.matches.is_empty() && self.excludes.is_empty() {
+            return true;
+        }
+        let text = item.text();
+
+        let matches = if self.match_all {
+            self.matches.iter().all(|re| re.is_match(&text))
+        } else {
+            self.matches.is_empty() || self.matches.iter().any(|re| re.is_match(&text))
+        };
+        matches && !self.excludes.iter().any(|re| re.is_match(&text))
+    }
 }
 
 static FEED_URL: &str = "https://stallman.org/rss/rss.xml";
 
 /// Returns all items in the stallman.org news feed.
 pub fn items() -> AnyResult<Vec<Item>> {
+    let items = items_if_changed(&mut Validators::default())?;
+    Ok(items.unwrap_or_default())
+}
+
+/// Returns all items in the feed, or None if the feed has not changed since the validators were
+/// received. The validators are updated from the response.
+pub fn items_if_changed(validators: &mut Validators) -> AnyResult<Option<Vec<Item>>> {
+    match fetch_if_changed(FEED_URL, validators).context("failed to get RSS feed")? {
+        Some(bytes) => parse_feed(&bytes).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn fetch_if_changed(url: &str, validators: &mut Validators) -> AnyResult<Option<Vec<u8>>> {
     let agent = ureq::AgentBuilder::new()
         .timeout(Duration::from_secs(20))
         .build();
-    let resp = agent
-        .get(&FEED_URL)
-        .call()
-        .context("failed to get RSS feed")?;
+
+    let mut request = agent.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.set("If-None-Match", etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.set("If-Modified-Since", last_modified);
+    }
+    let resp = request.call()?;
+    if resp.status() == 304 {
+        return Ok(None);
+    }
+
+    validators.etag = resp.header("ETag").map(str::to_string);
+    validators.last_modified = resp.header("Last-Modified").map(str::to_string);
 
     let mut reader = resp.into_reader();
     let mut bytes = vec![];
     reader.read_to_end(&mut bytes)?;
-    parse_feed(&bytes)
+    Ok(Some(bytes))
+}
+
+/// Tags the items and removes the items and article links that should not be fetched. The item
+/// filter is applied first, so that `--latest` counts the matching items.
+pub fn filter_items(items: &mut Vec<Item>, opts: &Opts) {
+    for item in items.iter_mut() {
+        item.tags = opts.tagger.tags(item);
+    }
+    items.retain(|item| opts.item_filter.is_match(item));
+    match &opts.fetch {
+        FetchType::Today | FetchType::Yesterday => {
+            let target_date = if let FetchType::Yesterday = opts.fetch {
+                Local::now() - chrono::Duration::days(1)
+            } else {
+                Local::now()
+            }
+            .date();
+
+            items.retain(|item| item.date.map(|d| d.date()) == Some(target_date));
+        }
+        FetchType::Latest(n) => items.truncate(*n),
+    }
+
+    for item in items.iter_mut() {
+        item.links
+            .retain(|link| match opts.domain_filter.skip_reason(link) {
+                Some(reason) => {
+                    debug!("skipping {}: {}", link, reason);
+                    false
+                }
+                None if opts.glossary.get(link).is_some() => {
+                    debug!("skipping {}: the definition is in the glossary", link);
+                    false
+                }
+                None => true,
+            });
+    }
 }
 
 fn parse_feed(feed: &[u8]) -> AnyResult<Vec<Item>> {
@@ -52,12 +196,13 @@ fn parse_feed(feed: &[u8]) -> AnyResult<Vec<Item>> {
         .map(|rss_item| {
             Ok(Item {
                 title: rss_item.title().unwrap_or("<Untitled>").to_string(),
-                date: parse_date(&rss_item),
+                date: parse_date(rss_item),
                 description: rss_item
                     .description()
                     .unwrap_or("<No description>")
                     .to_string(),
                 links: parse_article_links(rss_item)?,
+                tags: Vec::new(),
             })
         })
         .collect()
@@ -68,12 +213,13 @@ fn parse_date(item: &rss::Item) -> Option<DateTime<Local>> {
         .and_then(|date| DateTime::parse_from_rfc2822(date).ok().map(DateTime::from))
 }
 
-// Stallman puts the article links in the description so we get the links from there. Any links
-// that link to stallman.org are ignored.
+// Stallman puts the article links in the description so we get the links from there. Links to
+// stallman.org are ignored, links to domains denied by the user are removed by `filter_items`.
 fn parse_article_links(rss_item: &rss::Item) -> AnyResult<Vec<String>> {
     let desc = rss_item.description().unwrap_or("");
 
     let html = kuchiki::parse_html().one(desc);
+    let domains = DomainFilter::default();
 
     let mut links = html
         .select("a")
@@ -82,13 +228,7 @@ fn parse_article_links(rss_item: &rss::Item) -> AnyResult<Vec<String>> {
             let attrs = a.attributes.borrow();
             attrs.get("href").map(str::to_string)
         })
-        .filter(|a| {
-            util::is_http(a)
-                && !a.starts_with("https://stallman.org")
-                && !a.starts_with("https://www.stallman.org")
-                && !a.starts_with("http://stallman.org")
-                && !a.starts_with("http://www.stallman.org")
-        })
+        .filter(|a| util::is_http(a) && domains.skip_reason(a).is_none())
         .collect::<Vec<String>>();
 
     // Sometimes RMS gets a bit silly and puts multiple links to the same article so we use
@@ -112,6 +252,147 @@ fn parse_article_links(rss_item: &rss::Item) -> AnyResult<Vec<String>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tags::Tagger;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn filters_items_by_text() {
+        let item = |title: &str, description: &str| Item {
+            title: title.to_string(),
+            date: None,
+            description: description.to_string(),
+            links: vec![],
+            tags: vec![],
+        };
+        let items = vec![
+            item(
+                "Software patents",
+                "<a href=\"https://a.example\">EU patent court</a>",
+            ),
+            item(
+                "Surveillance",
+                "Cameras <a href=\"https://b.example\">everywhere</a>",
+            ),
+            item(
+                "Climate",
+                "Patents on <a href=\"https://c.example\">seeds</a>",
+            ),
+        ];
+        let titles = |items: &[Item]| items.iter().map(|i| i.title.clone()).collect::<Vec<_>>();
+        let patterns = |p: &[&str]| p.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        let any = ItemFilter::new(&patterns(&["patent", "camera"]), &[], false, &[]).unwrap();
+        let all = ItemFilter::new(&patterns(&["patent", "court"]), &[], true, &[]).unwrap();
+        let exclude =
+            ItemFilter::new(&patterns(&["patent"]), &patterns(&["seeds"]), false, &[]).unwrap();
+
+        let filtered = |filter: &ItemFilter| {
+            let mut items = items.clone();
+            items.retain(|item| filter.is_match(item));
+            titles(&items)
+        };
+        assert_eq!(
+            filtered(&any),
+            ["Software patents", "Surveillance", "Climate"]
+        );
+        assert_eq!(filtered(&all), ["Software patents"]);
+        assert_eq!(filtered(&exclude), ["Software patents"]);
+        assert!(ItemFilter::new(&patterns(&["("]), &[], false, &[]).is_err());
+
+        let tagger = Tagger::default();
+        let tag = ItemFilter::new(&[], &[], false, &patterns(&["Surveillance"])).unwrap();
+        let mut tagged = items.clone();
+        for item in tagged.iter_mut() {
+            item.tags = tagger.tags(item);
+        }
+        tagged.retain(|item| tag.is_match(item));
+        assert_eq!(titles(&tagged), ["Surveillance"]);
+    }
+
+    #[test]
+    fn filters_items_before_latest_and_removes_skipped_links() {
+        let opts = Opts::parse([
+            "tds",
+            "--latest",
+            "1",
+            "--match",
+            "patent",
+            "--deny-domain",
+            "denied.example",
+        ])
+        .unwrap();
+        let item = |title: &str, links: &[&str]| Item {
+            title: title.to_string(),
+            date: None,
+            description: String::new(),
+            links: links.iter().map(|l| l.to_string()).collect(),
+            tags: vec![],
+        };
+        let mut items = vec![
+            item("Surveillance", &["https://b.example/"]),
+            item(
+                "Software patents",
+                &[
+                    "https://a.example/court",
+                    "https://news.denied.example/patents",
+                    "https://www.gnu.org/philosophy/not-ipr.html",
+                ],
+            ),
+            item("Patents on seeds", &["https://c.example/"]),
+        ];
+
+        filter_items(&mut items, &opts);
+
+        // --latest 1 counts the matching items, not the first item of the feed.
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Software patents");
+        assert_eq!(items[0].links, ["https://a.example/court"]);
+    }
+
+    #[test]
+    fn sends_conditional_requests() {
+        // A server that answers the first request with the feed and the second with 304 if the
+        // validators are sent.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/rss.xml", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in &[
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Sun, 22 Nov 2020 10:00:00 GMT\r\n\
+                Content-Length: 4\r\nConnection: close\r\n\r\nfeed",
+                "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n",
+            ] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    request.push_str(&line.to_lowercase());
+                }
+                requests.push(request);
+                let mut stream = stream;
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+
+        let mut validators = Validators::default();
+        let feed = fetch_if_changed(&url, &mut validators).unwrap();
+        assert_eq!(feed.as_deref(), Some(&b"feed"[..]));
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+        assert!(fetch_if_changed(&url, &mut validators).unwrap().is_none());
+
+        let requests = handle.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(requests[1].contains("if-modified-since: sun, 22 nov 2020 10:00:00 gmt"));
+    }
 
     #[test]
     fn can_parse_article_links() {
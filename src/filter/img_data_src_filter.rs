@@ -84,7 +84,7 @@ impl<'a> ImgDataSrcFilter<'a> {
             Err(_) => return true,
         };
 
-        println!("loading to check if placeholder: {}", url.as_str());
+        debug!("loading to check if placeholder: {}", url.as_str());
         self.load_image_resource(url)
             .map(|res| res.size_bytes < 2000)
             .unwrap_or(true)
@@ -128,7 +128,7 @@ fn parse_chunk<'a>(chunk: &'a [&str]) -> (&'a str, Option<f32>) {
     let size = chunk.last().and_then(|size| {
         let mut first_num = String::new();
         for c in size.chars() {
-            if c.is_digit(10) || c == '.' {
+            if c.is_ascii_digit() || c == '.' {
                 first_num.push(c);
             } else {
                 break;
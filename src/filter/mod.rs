@@ -1,10 +1,12 @@
 mod img_data_src_filter;
 mod relative_links_filter;
+mod sanitize_filter;
 
 use html5ever::QualName;
 use img_data_src_filter::ImgDataSrcFilter;
 use kuchiki::NodeRef;
 use relative_links_filter::RelativeLinksFilter;
+use sanitize_filter::SanitizeFilter;
 use url::Url;
 
 pub fn do_global_filtering(node: &NodeRef, url: &Url) {
@@ -29,6 +31,18 @@ pub fn do_global_filtering(node: &NodeRef, url: &Url) {
 
     ImgDataSrcFilter::new(url).run(node);
     RelativeLinksFilter::new(url).run(node);
+    sanitize(node);
+}
+
+/// Removes all elements, attributes and URLs that are not known to be safe. This guarantees that
+/// the HTML does not contain any JavaScript.
+pub fn sanitize(node: &NodeRef) {
+    SanitizeFilter.run(node);
+}
+
+/// Returns true if the URL is relative or uses a scheme allowed by the sanitizer.
+pub fn is_safe_url(url: &str) -> bool {
+    sanitize_filter::is_safe_url(url, false)
 }
 
 pub fn remove_all(node: &NodeRef, selectors: &[&str]) -> usize {
@@ -75,7 +89,7 @@ pub fn remove_all_class(node: &NodeRef, classes: &[&str]) {
 /// Replaces all nodes matched by `selector` with a new node created with `name`.
 /// The children of the replaced node are appended to the new node.
 pub fn replace_all(base: &NodeRef, selector: &str, name: &QualName) {
-    if let Ok(selection) = base.select(&selector) {
+    if let Ok(selection) = base.select(selector) {
         for target in selection {
             let node = target.as_node();
             match (node.next_sibling(), node.parent()) {
@@ -0,0 +1,298 @@
+use html5ever::{namespace_url, ns};
+use kuchiki::{ElementData, NodeRef};
+
+// Removes everything from the HTML that is not explicitly allowed so that the output can never
+// run any scripts. Elements that are not allowed are removed together with their content if the
+// content is never displayed (like scripts) or dangerous (like SVG), otherwise the element is
+// replaced by its children. Attributes that are not allowed are removed, as are URL attributes
+// using any scheme except http, https and mailto (and data for images).
+pub struct SanitizeFilter;
+
+/// Elements that are kept as they are (minus any disallowed attributes).
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "html",
+    "head",
+    "body",
+    "a",
+    "abbr",
+    "address",
+    "article",
+    "b",
+    "bdi",
+    "bdo",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "main",
+    "mark",
+    "ol",
+    "p",
+    "picture",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "section",
+    "small",
+    "source",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+    "wbr",
+];
+
+/// Elements that are removed together with their content. Elements that are neither allowed
+/// nor dropped are replaced by their content.
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "frame", "frameset", "object", "embed",
+    "applet", "param", "svg", "math", "link", "meta", "base", "form", "input", "button", "select",
+    "option", "textarea", "video", "audio", "track", "canvas", "map", "area", "portal", "title",
+];
+
+/// Attributes allowed on all allowed elements.
+const GLOBAL_ATTRIBUTES: &[&str] = &["id", "class", "title", "lang", "dir"];
+
+/// Attributes allowed on specific elements.
+const ELEMENT_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href", "name"]),
+    ("img", &["src", "srcset", "sizes", "alt", "width", "height"]),
+    ("source", &["srcset", "sizes", "media", "type"]),
+    ("blockquote", &["cite"]),
+    ("q", &["cite"]),
+    ("del", &["cite", "datetime"]),
+    ("ins", &["cite", "datetime"]),
+    ("time", &["datetime"]),
+    ("ol", &["start", "reversed", "type"]),
+    ("li", &["value"]),
+    ("td", &["colspan", "rowspan", "headers"]),
+    ("th", &["colspan", "rowspan", "headers", "scope", "abbr"]),
+    ("col", &["span"]),
+    ("colgroup", &["span"]),
+    ("details", &["open"]),
+];
+
+/// Attributes containing URLs, which are checked for allowed schemes.
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite"];
+
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Inline image formats allowed in data URLs. SVG is not allowed since it can contain scripts.
+const ALLOWED_DATA_IMAGES: &[&str] = &[
+    "data:image/png",
+    "data:image/gif",
+    "data:image/jpeg",
+    "data:image/webp",
+];
+
+impl SanitizeFilter {
+    pub fn run(&self, node: &NodeRef) {
+        // Collect first since the tree is modified while iterating.
+        let nodes = node.descendants().collect::<Vec<_>>();
+
+        for node in nodes {
+            if node.as_comment().is_some() || node.as_doctype().is_some() {
+                node.detach();
+            } else if let Some(elem) = node.as_element() {
+                let name = &*elem.name.local;
+                let is_html = elem.name.ns == ns!(html);
+
+                if is_html && ALLOWED_ELEMENTS.contains(&name) {
+                    sanitize_attributes(elem);
+                } else if !is_html || DROPPED_ELEMENTS.contains(&name) {
+                    node.detach();
+                } else {
+                    unwrap(&node);
+                }
+            }
+        }
+    }
+}
+
+fn sanitize_attributes(elem: &ElementData) {
+    let name = &*elem.name.local;
+    let allowed = ELEMENT_ATTRIBUTES
+        .iter()
+        .find(|(element, _)| *element == name)
+        .map_or(&[][..], |(_, attributes)| *attributes);
+
+    let mut attrs = elem.attributes.borrow_mut();
+    attrs.map.retain(|attr_name, attr| {
+        let local = &*attr_name.local;
+        let is_allowed = attr_name.ns == ns!()
+            && (GLOBAL_ATTRIBUTES.contains(&local) || allowed.contains(&local));
+
+        is_allowed
+            && match local {
+                "srcset" => is_safe_srcset(&attr.value),
+                _ if URL_ATTRIBUTES.contains(&local) => is_safe_url(&attr.value, name == "img"),
+                _ => true,
+            }
+    });
+}
+
+pub fn is_safe_url(url: &str, is_image: bool) -> bool {
+    // Browsers ignore whitespace and control characters in the scheme, e.g. "java\tscript:".
+    let url = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_lowercase();
+
+    let scheme_end = url.find([':', '/', '?', '#']);
+    match scheme_end {
+        Some(i) if url[i..].starts_with(':') => {
+            ALLOWED_SCHEMES.contains(&&url[..i])
+                || (is_image && ALLOWED_DATA_IMAGES.iter().any(|d| url.starts_with(d)))
+        }
+        // Relative URL.
+        _ => true,
+    }
+}
+
+fn is_safe_srcset(srcset: &str) -> bool {
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+        .all(|url| is_safe_url(url, true))
+}
+
+/// Replaces the node with its children.
+fn unwrap(node: &NodeRef) {
+    for child in node.children() {
+        node.insert_before(child);
+    }
+    node.detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+
+    fn sanitize(html: &str) -> String {
+        let node = kuchiki::parse_html().one(html);
+        SanitizeFilter.run(&node);
+        node.select_first("body")
+            .unwrap()
+            .as_node()
+            .children()
+            .map(|c| c.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_allowed_content() {
+        let html = r#"<p class="x">Some <a href="https://example.com/">text</a><img alt="a" src="https://example.com/a.png"></p>"#;
+        assert_eq!(sanitize(html), html);
+        assert_eq!(
+            sanitize(r##"<a href="mailto:a@example.com">a</a><a href="#top">b</a>"##),
+            r##"<a href="mailto:a@example.com">a</a><a href="#top">b</a>"##
+        );
+    }
+
+    #[test]
+    fn unwraps_unknown_elements() {
+        assert_eq!(
+            sanitize("<p><font color=red>red</font> <custom-el>text</custom-el></p>"),
+            "<p>red text</p>"
+        );
+    }
+
+    #[test]
+    fn removes_scripts() {
+        let payloads = [
+            "<script>alert(1)</script>",
+            "<svg><script>alert(1)</script></svg>",
+            "<svg onload=alert(1)></svg>",
+            "<math><mi xlink:href=\"javascript:alert(1)\">x</mi></math>",
+            "<object data=\"javascript:alert(1)\"></object>",
+            "<embed src=\"javascript:alert(1)\">",
+            "<iframe srcdoc=\"<script>alert(1)</script>\"></iframe>",
+            "<meta http-equiv=\"refresh\" content=\"0;url=javascript:alert(1)\">",
+            "<link rel=\"stylesheet\" href=\"javascript:alert(1)\">",
+            "<base href=\"javascript:alert(1)//\">",
+            "<form action=\"javascript:alert(1)\"><button>x</button></form>",
+            "<template><script>alert(1)</script></template>",
+            "<style>@import 'javascript:alert(1)';</style>",
+            "<!--<img src=x onerror=alert(1)>-->",
+        ];
+        for payload in &payloads {
+            assert_eq!(sanitize(payload), "", "{}", payload);
+        }
+    }
+
+    #[test]
+    fn removes_dangerous_attributes() {
+        let payloads = [
+            ("<img src=x onerror=alert(1)>", "<img src=\"x\">"),
+            (
+                "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\"></noscript>",
+                "<img src=\"x\">\"&gt;",
+            ),
+            ("<p onclick=alert(1) onmouseover=alert(1)>x</p>", "<p>x</p>"),
+            ("<div style=\"background:url(javascript:alert(1))\">x</div>", "<div>x</div>"),
+            ("<a href=\"javascript:alert(1)\">x</a>", "<a>x</a>"),
+            ("<a href=\" JaVa&#09;ScRiPt:alert(1)\">x</a>", "<a>x</a>"),
+            ("<a href=\"java&#x0A;script:alert(1)\">x</a>", "<a>x</a>"),
+            ("<a href=\"vbscript:msgbox(1)\">x</a>", "<a>x</a>"),
+            ("<a href=\"data:text/html,<script>alert(1)</script>\">x</a>", "<a>x</a>"),
+            ("<img src=\"data:image/svg+xml;base64,PHN2Zz4=\">", "<img>"),
+            ("<img srcset=\"a.png 1x, javascript:alert(1) 2x\">", "<img>"),
+            ("<blockquote cite=\"javascript:alert(1)\">x</blockquote>", "<blockquote>x</blockquote>"),
+            ("<a href=\"https://example.com\" target=\"_blank\" ping=\"https://t.example\">x</a>", "<a href=\"https://example.com\">x</a>"),
+        ];
+        for (payload, expected) in &payloads {
+            assert_eq!(&sanitize(payload), expected, "{}", payload);
+        }
+    }
+
+    #[test]
+    fn keeps_inline_raster_images() {
+        let html = "<img src=\"data:image/png;base64,iVBORw0KGgo=\">";
+        assert_eq!(sanitize(html), html);
+    }
+}
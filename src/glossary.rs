@@ -0,0 +1,369 @@
+use crate::util;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use chrono::Local;
+use kuchiki::traits::TendrilSink;
+use kuchiki::NodeRef;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ureq::{Agent, AgentBuilder};
+use url::Url;
+
+// RMS links the same pages explaining his terms again and again, mostly from the gnu.org
+// philosophy section. These pages are not fetched as articles; their definitions from the
+// glossary are shown as notes instead. The built-in glossary can be extended and refreshed with
+// `tds glossary`, which stores the glossary in the data directory.
+
+const BUILTIN_GLOSSARY: &str = include_str!("../resources/glossary.json");
+
+/// Definitions longer than this are cut at the end of a sentence.
+const MAX_DEFINITION_LENGTH: usize = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub url: String,
+    pub term: String,
+    pub definition: String,
+}
+
+/// The cached glossary file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cache {
+    /// When the glossary was last updated (RFC 3339).
+    updated: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Glossary {
+    entries: Vec<Entry>,
+}
+
+impl Glossary {
+    /// Loads the cached glossary merged over the built-in one. The built-in glossary is used if
+    /// there is no cache. A broken cache gives a warning and the entries that can still be read
+    /// are used.
+    pub fn load() -> Glossary {
+        match cache_path() {
+            Ok(path) => Glossary::load_from(&path),
+            Err(_) => Glossary::default(),
+        }
+    }
+
+    fn load_from(path: &Path) -> Glossary {
+        // Entries added to the built-in glossary after the cache was written are kept.
+        let mut glossary = Glossary::default();
+        if !path.is_file() {
+            return glossary;
+        }
+        let (entries, error) = read_cache(path);
+        if let Some(err) = error {
+            warn!(
+                "invalid glossary {}: {:#} - using the built-in glossary and the {} entries that \
+                could be read, run `tds glossary --update` to repair it",
+                path.display(),
+                err,
+                entries.len()
+            );
+        }
+        for entry in entries {
+            glossary.insert(entry);
+        }
+        glossary
+    }
+
+    /// Returns the entry of a glossary page.
+    pub fn get(&self, url: &str) -> Option<&Entry> {
+        let url_key = key(url)?;
+        self.entries
+            .iter()
+            .find(|entry| key(&entry.url).as_deref() == Some(url_key.as_str()))
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Downloads the definitions of all entries and of the added pages again, and saves the
+    /// glossary. Entries that can't be downloaded keep their old definition.
+    pub fn update(&mut self, add: &[String]) -> AnyResult<()> {
+        let agent = AgentBuilder::new().timeout(Duration::from_secs(20)).build();
+        self.refresh(add, |url| fetch_entry(&agent, url));
+        self.save_to(&cache_path()?)
+    }
+
+    fn refresh(&mut self, add: &[String], fetch: impl Fn(&str) -> AnyResult<Entry>) {
+        let urls = self
+            .entries
+            .iter()
+            .map(|entry| entry.url.clone())
+            .chain(add.iter().cloned())
+            .collect::<Vec<_>>();
+
+        for url in urls {
+            match fetch(&url) {
+                Ok(entry) => {
+                    info!("{} ... Ok", url);
+                    self.insert(entry);
+                }
+                Err(err) => warn!("{} ... Error: {:#}", url, err),
+            }
+        }
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        let entry_key = key(&entry.url);
+        match self.entries.iter_mut().find(|e| key(&e.url) == entry_key) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> AnyResult<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let cache = Cache {
+            updated: Local::now().to_rfc3339(),
+            entries: self.entries.clone(),
+        };
+        // Written to a temp file first so that an interrupted update doesn't leave a broken cache.
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(&cache)?)
+            .and_then(|_| fs::rename(&temp, path))
+            .with_context(|| format!("failed to write glossary {}", path.display()))
+    }
+}
+
+impl Default for Glossary {
+    /// The built-in glossary.
+    fn default() -> Self {
+        Glossary {
+            entries: serde_json::from_str(BUILTIN_GLOSSARY).expect("invalid built-in glossary"),
+        }
+    }
+}
+
+fn cache_path() -> AnyResult<PathBuf> {
+    Ok(util::data_dir()?.join("glossary.json"))
+}
+
+/// Reads the entries of the cache. If the cache is broken, the entries that can still be read are
+/// returned together with the error.
+fn read_cache(path: &Path) -> (Vec<Entry>, Option<Error>) {
+    let cache = match fs::read_to_string(path) {
+        Ok(cache) => cache,
+        Err(err) => return (Vec::new(), Some(err.into())),
+    };
+    match serde_json::from_str::<Cache>(&cache) {
+        Ok(cache) => (cache.entries, None),
+        Err(err) => {
+            let entries = serde_json::from_str::<serde_json::Value>(&cache)
+                .ok()
+                .and_then(|cache| cache.get("entries")?.as_array().cloned())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|entry| serde_json::from_value(entry).ok())
+                .collect();
+            (entries, Some(err.into()))
+        }
+    }
+}
+
+/// Glossary pages are linked in different ways, e.g. with or without www. and the language
+/// suffix (not-ipr.en.html). The key is the URL without these differences.
+fn key(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    let host = host.trim_start_matches("www.");
+    let path = url.path().replace(".en.html", ".html");
+    let path = path.trim_end_matches('/');
+    Some(match url.fragment() {
+        Some(fragment) => format!("{}{}#{}", host, path, fragment),
+        None => format!("{}{}", host, path),
+    })
+}
+
+fn fetch_entry(agent: &Agent, url: &str) -> AnyResult<Entry> {
+    let mut html = String::new();
+    agent
+        .get(url)
+        .call()
+        .context("failed to get page")?
+        .into_reader()
+        .read_to_string(&mut html)
+        .context("failed to read page")?;
+    let fragment = Url::parse(url)?.fragment().map(str::to_string);
+    parse_entry(url, &html, fragment.as_deref())
+}
+
+/// Finds the term and definition in a glossary page. For a link to a section of a page (like
+/// words-to-avoid.html#Piracy), the term is the section heading and the definition its first
+/// paragraph. Otherwise the term is the page heading and the definition the first paragraph.
+fn parse_entry(url: &str, html: &str, fragment: Option<&str>) -> AnyResult<Entry> {
+    let doc = kuchiki::parse_html().one(html);
+
+    let (heading, first_paragraph) = match fragment {
+        Some(fragment) => {
+            let heading = doc
+                .select(&format!("[id=\"{}\"], [name=\"{}\"]", fragment, fragment))
+                .ok()
+                .and_then(|mut s| s.next())
+                .ok_or_else(|| anyhow!("section `{}` not found", fragment))?;
+            let heading = heading.as_node().clone();
+            let paragraph = heading.following_siblings().find(is_paragraph);
+            (heading, paragraph)
+        }
+        None => {
+            let heading = doc
+                .select_first("h2, h1")
+                .map_err(|_| anyhow!("page heading not found"))?;
+            let heading = heading.as_node().clone();
+            let paragraph = heading
+                .following_siblings()
+                .filter(is_paragraph)
+                .find(|node| normalize(&node.text_contents()).len() > 80);
+            (heading, paragraph)
+        }
+    };
+
+    let term = normalize(&heading.text_contents())
+        .trim_matches(|c| "\"“”".contains(c))
+        .to_string();
+    let definition = first_paragraph
+        .map(|p| shorten(&normalize(&p.text_contents())))
+        .ok_or_else(|| anyhow!("definition not found"))?;
+    if term.is_empty() {
+        bail!("term not found");
+    }
+
+    Ok(Entry {
+        url: url.to_string(),
+        term,
+        definition,
+    })
+}
+
+fn is_paragraph(node: &NodeRef) -> bool {
+    node.as_element()
+        .is_some_and(|element| &*element.name.local == "p")
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cuts the text after the last sentence that fits in MAX_DEFINITION_LENGTH.
+fn shorten(text: &str) -> String {
+    if text.chars().count() <= MAX_DEFINITION_LENGTH {
+        return text.to_string();
+    }
+    let cut = text
+        .char_indices()
+        .nth(MAX_DEFINITION_LENGTH)
+        .map_or(text.len(), |(i, _)| i);
+    match text[..cut].rfind(". ") {
+        Some(end) => text[..=end].to_string(),
+        None => format!("{}...", text[..cut].trim_end()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_entries_by_normalized_url() {
+        let glossary = Glossary::default();
+
+        let entry = glossary
+            .get("http://gnu.org/philosophy/not-ipr.en.html")
+            .unwrap();
+        assert_eq!(entry.term, "Intellectual property");
+        assert!(glossary
+            .get("https://www.gnu.org/philosophy/words-to-avoid.html#Piracy")
+            .is_some());
+        assert!(glossary
+            .get("https://www.gnu.org/philosophy/words-to-avoid.html")
+            .is_none());
+        assert!(glossary.get("https://example.com/not-ipr.html").is_none());
+    }
+
+    #[test]
+    fn parses_glossary_pages() {
+        let html = r#"
+            <div id="content">
+            <h2>Words to Avoid</h2>
+            <p>There are a number of words and phrases that we recommend avoiding, or avoiding
+            in certain contexts and usages.</p>
+            <h3 id="Piracy">&ldquo;Piracy&rdquo;</h3>
+            <p>Publishers often refer to copying they don't approve of as &ldquo;piracy.&rdquo;</p>
+            <p>More text.</p>
+            </div>
+            "#;
+        let url = "https://www.gnu.org/philosophy/words-to-avoid.html";
+
+        let section = parse_entry(url, html, Some("Piracy")).unwrap();
+        assert_eq!(section.term, "Piracy");
+        assert_eq!(
+            section.definition,
+            "Publishers often refer to copying they don't approve of as “piracy.”"
+        );
+        let page = parse_entry(url, html, None).unwrap();
+        assert_eq!(page.term, "Words to Avoid");
+        assert!(page.definition.starts_with("There are a number of words"));
+        assert!(parse_entry(url, html, Some("Missing")).is_err());
+    }
+
+    #[test]
+    fn keeps_added_entries_when_updating() {
+        let dir = std::env::temp_dir().join(format!("tds-glossary-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("glossary.json");
+        let added = "https://www.gnu.org/philosophy/words-to-avoid.html#Monetize";
+        let fetch = |definition: &'static str| {
+            move |url: &str| {
+                Ok(Entry {
+                    url: url.to_string(),
+                    term: "Term".to_string(),
+                    definition: definition.to_string(),
+                })
+            }
+        };
+
+        // tds glossary --add
+        let mut glossary = Glossary::load_from(&path);
+        glossary.refresh(&[added.to_string()], fetch("added"));
+        glossary.save_to(&path).unwrap();
+
+        // tds glossary --update
+        let mut glossary = Glossary::load_from(&path);
+        glossary.refresh(&[], fetch("updated"));
+        glossary.save_to(&path).unwrap();
+
+        let glossary = Glossary::load_from(&path);
+        assert_eq!(glossary.get(added).unwrap().definition, "updated");
+        assert_eq!(
+            glossary.entries().len(),
+            Glossary::default().entries().len() + 1
+        );
+
+        // The entries that can be read are kept if the cache is broken.
+        fs::write(
+            &path,
+            format!(
+                r#"{{"entries": [{{"url": "{}", "term": "Monetize", "definition": "d"}}, {{}}]}}"#,
+                added
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            Glossary::load_from(&path).get(added).unwrap().term,
+            "Monetize"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use crate::archive;
+use crate::export::{DigestItem, FetchStatus};
+use crate::report::Report;
+use crate::resolve::ResolvedItem;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Hooks are user commands that are run with the shell when something happens: for every fetched
+// item, and after the output has been written. The event data is passed as JSON on stdin and the
+// most useful fields are also set as environment variables.
+
+/// A command that is run after the output has been written.
+#[derive(Debug)]
+pub enum PostRunHook {
+    /// A shell command. The output path is passed as the first argument ($1).
+    Shell(String),
+    /// A program, like a browser, that is started with the output path as its only argument.
+    /// It is not waited for.
+    Open(PathBuf),
+}
+
+/// What post-run hooks receive as JSON on stdin.
+#[derive(Debug, Serialize)]
+pub struct RunSummary<'a> {
+    /// The file the output was written to. None if it was written to stdout or sent by email.
+    pub output: Option<&'a Path>,
+    pub format: &'a str,
+    /// The number of feed items in the output.
+    pub items: usize,
+    #[serde(flatten)]
+    pub report: &'a Report,
+}
+
+/// Runs the hooks in order. A failing hook is logged and does not stop the other hooks.
+pub fn run_post_run_hooks(hooks: &[PostRunHook], summary: &RunSummary) {
+    for hook in hooks {
+        if let Err(err) = run_post_run_hook(hook, summary) {
+            warn!("{:#}", err);
+        }
+    }
+}
+
+fn run_post_run_hook(hook: &PostRunHook, summary: &RunSummary) -> AnyResult<()> {
+    let output = summary.output.unwrap_or_else(|| Path::new(""));
+    match hook {
+        PostRunHook::Shell(command) => {
+            let env = [
+                ("TDS_OUTPUT", output.display().to_string()),
+                ("TDS_FORMAT", summary.format.to_string()),
+                ("TDS_ITEMS", summary.items.to_string()),
+                ("TDS_ARTICLES_OK", summary.report.ok.to_string()),
+                ("TDS_ARTICLES_FAILED", summary.report.failed.to_string()),
+            ];
+            let arg = output.display().to_string();
+            run(command, &[&arg], &env, &serde_json::to_string(summary)?)
+        }
+        PostRunHook::Open(program) => {
+            if summary.output.is_none() {
+                bail!(
+                    "{} can only open output written to a file",
+                    program.display()
+                );
+            }
+            Command::new(program)
+                .arg(output)
+                .stdout(Stdio::null())
+                .stdin(Stdio::null())
+                .spawn()
+                .with_context(|| format!("failed to start {}", program.display()))?;
+            Ok(())
+        }
+    }
+}
+
+/// Runs the item hooks for every item. The item is passed as JSON on stdin, in the same format as
+/// the items in the JSON export.
+pub fn run_item_hooks(hooks: &[String], resolved: &[ResolvedItem]) {
+    for resolved in resolved {
+        let id = archive::item_id(&resolved.item).unwrap_or_default();
+        let item = DigestItem::new(resolved);
+        for hook in hooks {
+            if let Err(err) = run_item_hook(hook, &id, &item) {
+                warn!("{:#}", err);
+            }
+        }
+    }
+}
+
+fn run_item_hook(command: &str, id: &str, item: &DigestItem) -> AnyResult<()> {
+    let failed = item
+        .articles
+        .iter()
+        .filter(|a| a.status == FetchStatus::Error)
+        .count();
+    let env = [
+        ("TDS_ITEM_ID", id.to_string()),
+        ("TDS_ITEM_TITLE", item.title.clone()),
+        ("TDS_ITEM_DATE", item.date.clone().unwrap_or_default()),
+        ("TDS_ITEM_LINKS", item.links.join("\n")),
+        ("TDS_ITEM_TAGS", item.tags.join(",")),
+        (
+            "TDS_ARTICLES_OK",
+            (item.articles.len() - failed).to_string(),
+        ),
+        ("TDS_ARTICLES_FAILED", failed.to_string()),
+    ];
+    run(command, &[], &env, &serde_json::to_string(item)?)
+}
+
+fn run(command: &str, args: &[&str], env: &[(&str, String)], stdin: &str) -> AnyResult<()> {
+    let mut child = shell(command, args)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::piped())
+        // Keeps the hook output apart from the digest if it is written to stdout.
+        .stdout(io::stderr())
+        .spawn()
+        .with_context(|| format!("failed to run hook `{}`", command))?;
+
+    // The hook may exit without reading its input.
+    let _ = child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(stdin.as_bytes());
+
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("hook `{}` failed with {}", command, status);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn shell(command: &str, args: &[&str]) -> Command {
+    let mut shell = Command::new("sh");
+    // The first argument after the command is $0.
+    shell.arg("-c").arg(command).arg("tds").args(args);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str, args: &[&str]) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command).args(args);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn passes_run_summary_to_post_run_hooks() {
+        let dir = std::env::temp_dir().join(format!("tds-hook-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let result = dir.join("result");
+        let report = Report {
+            generated: "2020-11-23T08:00:00+01:00".to_string(),
+            ok: 2,
+            failed: 1,
+            links: vec![],
+        };
+        let summary = RunSummary {
+            output: Some(Path::new("/tmp/tds.html")),
+            format: "html",
+            items: 1,
+            report: &report,
+        };
+        let command = format!(
+            "{{ echo \"$1 $TDS_FORMAT $TDS_ARTICLES_OK $TDS_ARTICLES_FAILED\"; cat; }} > {}",
+            result.display()
+        );
+
+        run_post_run_hooks(&[PostRunHook::Shell(command)], &summary);
+
+        let written = fs::read_to_string(&result).unwrap();
+        let mut lines = written.lines();
+        assert_eq!(lines.next(), Some("/tmp/tds.html html 2 1"));
+        let json: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(json["output"], "/tmp/tds.html");
+        assert_eq!(json["failed"], 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
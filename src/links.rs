@@ -0,0 +1,171 @@
+use kuchiki::NodeRef;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+// The outbound links of an article are its references: the reports, studies and other articles
+// it cites. Links that are only there for navigation, sharing or ads are dropped.
+
+/// A link found in an article body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub url: String,
+    /// The anchor text.
+    pub text: String,
+}
+
+/// Anchor texts of navigational links (lowercase).
+const NAVIGATION_TEXTS: &[&str] = &[
+    "advertisement",
+    "click here to subscribe",
+    "comments",
+    "contact us",
+    "continue reading",
+    "donate",
+    "home",
+    "log in",
+    "login",
+    "more",
+    "newsletter",
+    "next",
+    "previous",
+    "privacy policy",
+    "read more",
+    "share",
+    "sign in",
+    "sign up",
+    "subscribe",
+    "terms of service",
+    "terms of use",
+    "tweet",
+];
+
+/// Path segments of navigational pages such as tag and author listings.
+const NAVIGATION_PATHS: &[&str] = &[
+    "account",
+    "author",
+    "authors",
+    "category",
+    "login",
+    "newsletter",
+    "newsletters",
+    "profile",
+    "signin",
+    "subscribe",
+    "subscription",
+    "tag",
+    "tags",
+    "topic",
+    "topics",
+];
+
+/// Ad, tracking and share services. Subdomains match too.
+const AD_DOMAINS: &[&str] = &[
+    "adclick.g.doubleclick.net",
+    "amazon-adsystem.com",
+    "doubleclick.net",
+    "googleadservices.com",
+    "outbrain.com",
+    "taboola.com",
+];
+
+/// URL prefixes of share buttons.
+const SHARE_URLS: &[&str] = &[
+    "https://twitter.com/intent/",
+    "https://twitter.com/share",
+    "https://www.facebook.com/sharer",
+    "https://facebook.com/sharer",
+    "https://www.linkedin.com/shareArticle",
+    "https://reddit.com/submit",
+    "https://www.reddit.com/submit",
+];
+
+/// Returns the outbound links in the article body in document order, without duplicates.
+/// The links must be absolute, i.e. the body must have been filtered with the relative links
+/// filter.
+pub fn article_links(node: &NodeRef, article_url: &Url) -> Vec<Link> {
+    let anchors = match node.select("a[href]") {
+        Ok(anchors) => anchors,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut links: Vec<Link> = Vec::new();
+    for anchor in anchors {
+        let href = match anchor.attributes.borrow().get("href") {
+            Some(href) => href.trim().to_string(),
+            None => continue,
+        };
+        let text = anchor
+            .text_contents()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if is_reference(&href, &text, article_url) && !links.iter().any(|l| l.url == href) {
+            links.push(Link { url: href, text });
+        }
+    }
+    links
+}
+
+fn is_reference(href: &str, text: &str, article_url: &Url) -> bool {
+    let url = match Url::parse(href) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        _ => return false,
+    };
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+
+    // Links within the article itself.
+    let same_page = url.host_str() == article_url.host_str() && url.path() == article_url.path();
+    // Image links and the like have no text to show.
+    let navigation = text.is_empty() || NAVIGATION_TEXTS.contains(&text.to_lowercase().as_str());
+    let navigation_page = url.path_segments().is_some_and(|mut segments| {
+        segments.any(|s| NAVIGATION_PATHS.contains(&s.to_lowercase().as_str()))
+    });
+    let ad = AD_DOMAINS
+        .iter()
+        .any(|d| host == *d || host.ends_with(&format!(".{}", d)));
+    let share = SHARE_URLS.iter().any(|prefix| href.starts_with(prefix));
+
+    !(same_page || navigation || navigation_page || ad || share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+    use std::str::FromStr;
+
+    #[test]
+    fn finds_references() {
+        let html = r#"
+            <p>A <a href="https://example.org/report.pdf">new  report</a> found that
+            <a href="https://news.example.com/2020/11/study">a study</a> was
+            <a href="https://example.org/report.pdf">right</a>.</p>
+            <p><a href="https://news.example.com/article#comments">Comments</a>
+            <a href="https://news.example.com/tag/patents">Patents</a>
+            <a href="https://news.example.com/">Home</a>
+            <a href="https://twitter.com/intent/tweet?url=x">Tweet this</a>
+            <a href="https://ad.doubleclick.net/x">Buy now</a>
+            <a href="https://example.org/photo"><img src="https://example.org/p.jpg"></a>
+            <a href="mailto:editor@example.com">Write to us</a></p>
+            "#;
+        let node = kuchiki::parse_html().one(html);
+        let url = Url::from_str("https://news.example.com/article").unwrap();
+
+        let links = article_links(&node, &url);
+
+        assert_eq!(
+            links,
+            vec![
+                Link {
+                    url: "https://example.org/report.pdf".to_string(),
+                    text: "new report".to_string()
+                },
+                Link {
+                    url: "https://news.example.com/2020/11/study".to_string(),
+                    text: "a study".to_string()
+                },
+            ]
+        );
+    }
+}
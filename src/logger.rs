@@ -0,0 +1,71 @@
+use crate::progress;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::str::FromStr;
+
+// A logger that writes to stderr so that log messages never end up in the output when it is
+// written to stdout. Messages are printed above the progress display if it is shown.
+// Messages logged by dependencies are only shown at the trace level.
+
+/// Environment variable that sets the log level (off, error, warn, info, debug or trace) if
+/// neither -v nor -q is given.
+pub const LOG_ENV: &str = "TDS_LOG";
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let is_own = metadata.target() == "tds" || metadata.target().starts_with("tds::");
+        metadata.level() <= log::max_level() && (is_own || log::max_level() == LevelFilter::Trace)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        progress::print_above(|| match record.level() {
+            Level::Info => eprintln!("{}", record.args()),
+            Level::Warn => eprintln!("WARNING: {}", record.args()),
+            level => eprintln!("{}: {}", level, record.args()),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Returns the log level given the number of -v flags, the -q flag and the value of the log
+/// environment variable. The flags take precedence over the environment variable.
+pub fn level(verbose: u64, quiet: bool, env: Option<&str>) -> AnyResult<LevelFilter> {
+    Ok(match (verbose, quiet, env) {
+        (_, true, _) => LevelFilter::Error,
+        (0, _, Some(env)) => LevelFilter::from_str(env)
+            .map_err(|_| anyhow!("invalid log level `{}` in {}", env, LOG_ENV))?,
+        (0, _, None) => LevelFilter::Info,
+        (1, _, _) => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_override_env() {
+        assert_eq!(level(0, false, None).unwrap(), LevelFilter::Info);
+        assert_eq!(level(1, false, None).unwrap(), LevelFilter::Debug);
+        assert_eq!(level(3, false, Some("warn")).unwrap(), LevelFilter::Trace);
+        assert_eq!(level(0, true, Some("debug")).unwrap(), LevelFilter::Error);
+        assert_eq!(level(0, false, Some("WARN")).unwrap(), LevelFilter::Warn);
+        assert!(level(0, false, Some("loud")).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+use crate::options::{MailOpts, Transport};
+use crate::resolve::ResolvedItem;
+use crate::util;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use chrono::{DateTime, Local};
+use kuchiki::traits::TendrilSink;
+use rayon::prelude::*;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use ureq::AgentBuilder;
+
+// Builds the digest as a MIME email: a multipart/related message containing a
+// multipart/alternative part with the plain text and HTML versions of the digest, followed by the
+// images of the HTML version as inline attachments referenced by Content-ID. All parts are base64
+// encoded so that the message is 7-bit clean.
+
+/// Images larger than this are not attached and are loaded from the web by the mail client.
+const MAX_IMAGE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// The line length limit for base64 encoded bodies (RFC 2045).
+const BASE64_LINE_LENGTH: usize = 76;
+
+/// An image attached to the message and referenced from the HTML by its Content-ID.
+#[derive(Debug)]
+pub struct InlineImage {
+    pub cid: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Builds the email message for the digest. The images in the HTML are downloaded and attached.
+pub fn message(items: &[ResolvedItem], html: &str, opts: &MailOpts) -> String {
+    let now = Local::now();
+    let subject = opts
+        .subject
+        .clone()
+        .unwrap_or_else(|| format!("The Daily Stallman - {}", now.format("%B %-d, %Y")));
+    let (html, images) = inline_images(html);
+
+    build(opts, &subject, now, &text(items), &html, &images)
+}
+
+/// Delivers the message with the configured transport. Messages delivered to a file are written
+/// to `path` (or stdout), which is appended to in mbox format if the file name ends in .mbox.
+pub fn deliver(message: &str, opts: &MailOpts, path: &Path) -> AnyResult<()> {
+    match &opts.transport {
+        Transport::File if path.extension().is_some_and(|ext| ext == "mbox") => {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(mbox_entry(message, &opts.from, Local::now()).as_bytes())?;
+        }
+        Transport::File => util::write_file(path, message)?,
+        Transport::Sendmail(sendmail) => {
+            let mut child = Command::new(sendmail)
+                .arg("-i")
+                .arg("-f")
+                .arg(&opts.from)
+                .arg("--")
+                .args(&opts.to)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .with_context(|| format!("failed to run {}", sendmail.display()))?;
+            // sendmail expects the local line ending.
+            let message = message.replace("\r\n", "\n");
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(message.as_bytes())?;
+            let status = child.wait()?;
+            if !status.success() {
+                bail!("{} failed with {}", sendmail.display(), status);
+            }
+        }
+        Transport::Smtp(server) => {
+            smtp_send(server, &opts.from, &opts.to, message)
+                .with_context(|| format!("failed to send mail with SMTP server {}", server))?;
+        }
+    }
+    Ok(())
+}
+
+/// A simple check that the address can be used in headers and SMTP commands. The mail server
+/// does the real validation.
+pub fn is_valid_address(address: &str) -> bool {
+    let mut parts = address.splitn(2, '@');
+    let (local, domain) = (parts.next().unwrap_or_default(), parts.next());
+    !local.is_empty()
+        && domain.is_some_and(|domain| !domain.is_empty())
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>,;\"()[]\\".contains(c))
+}
+
+fn build(
+    opts: &MailOpts,
+    subject: &str,
+    date: DateTime<Local>,
+    text: &str,
+    html: &str,
+    images: &[InlineImage],
+) -> String {
+    let id = util::stable_hash(&format!("{}\n{}", date.to_rfc3339(), subject));
+    let related = format!("=_tds_related_{:016x}", id);
+    let alternative = format!("=_tds_alternative_{:016x}", id);
+
+    let mut message = String::new();
+    let mut line = |line: &str| {
+        message.push_str(line);
+        message.push_str("\r\n");
+    };
+
+    line(&format!("From: {}", opts.from));
+    line(&format!("To: {}", opts.to.join(", ")));
+    line(&format!("Subject: {}", encode_header("Subject", subject)));
+    line(&format!("Date: {}", date.to_rfc2822()));
+    line(&format!(
+        "Message-ID: <{}.{:016x}@the-daily-stallman>",
+        date.timestamp(),
+        id
+    ));
+    line("MIME-Version: 1.0");
+    line("Content-Type: multipart/related; type=\"multipart/alternative\";");
+    line(&format!(" boundary=\"{}\"", related));
+    line("");
+    line("This is a multipart message in MIME format.");
+    line("");
+    line(&format!("--{}", related));
+    line("Content-Type: multipart/alternative;");
+    line(&format!(" boundary=\"{}\"", alternative));
+    line("");
+    for (content_type, body) in &[("text/plain", text), ("text/html", html)] {
+        line(&format!("--{}", alternative));
+        line(&format!("Content-Type: {}; charset=utf-8", content_type));
+        line("Content-Transfer-Encoding: base64");
+        line("");
+        line(&encode_base64(body.as_bytes()));
+    }
+    line(&format!("--{}--", alternative));
+    for image in images {
+        line(&format!("--{}", related));
+        line(&format!("Content-Type: {}", image.content_type));
+        line("Content-Transfer-Encoding: base64");
+        line(&format!("Content-ID: <{}>", image.cid));
+        line("Content-Disposition: inline");
+        line("");
+        line(&encode_base64(&image.data));
+    }
+    line(&format!("--{}--", related));
+
+    message
+}
+
+/// The plain text version of the digest.
+fn text(items: &[ResolvedItem]) -> String {
+    let mut text = String::new();
+
+    for resolved in items {
+        let description = kuchiki::parse_html()
+            .one(resolved.item.description.as_str())
+            .text_contents();
+        if resolved.actions.is_empty() {
+            text.push_str("RMS says:\n\n");
+        } else {
+            text.push_str("Call to action:\n\n");
+        }
+        text.push_str(description.trim());
+        text.push_str("\n\n");
+        for action in &resolved.actions {
+            text.push_str(&format!("Take action: {}\n\n", action));
+        }
+
+        for art in &resolved.articles {
+            text.push_str(&format!("{}\n", "-".repeat(72)));
+            text.push_str(&format!(
+                "{}\n{}\n",
+                art.title.as_deref().unwrap_or(&art.url),
+                art.url
+            ));
+            let mut byline = art.authors.join(", ");
+            if let Some(date) = &art.publishing_date {
+                if !byline.is_empty() {
+                    byline.push_str(" - ");
+                }
+                byline.push_str(&date.to_string());
+            }
+            if !byline.is_empty() {
+                text.push_str(&format!("{}\n", byline));
+            }
+            text.push_str(&format!("\n{}\n\n", art.text.trim()));
+        }
+        text.push_str(&format!("{}\n\n", "=".repeat(72)));
+    }
+    text
+}
+
+/// Downloads the images in the HTML and replaces their URLs with Content-ID references. Images
+/// that can't be downloaded are left as they are.
+fn inline_images(html: &str) -> (String, Vec<InlineImage>) {
+    let doc = kuchiki::parse_html().one(html);
+    let imgs = match doc.select("img[src]") {
+        Ok(imgs) => imgs.collect::<Vec<_>>(),
+        Err(_) => return (html.to_string(), vec![]),
+    };
+
+    let mut urls = imgs
+        .iter()
+        .filter_map(|img| img.attributes.borrow().get("src").map(str::to_string))
+        .filter(|src| util::is_http(src))
+        .collect::<Vec<_>>();
+    urls.sort();
+    urls.dedup();
+
+    let agent = AgentBuilder::new().timeout(Duration::from_secs(20)).build();
+    let images = urls
+        .par_iter()
+        .filter_map(|url| {
+            let resp = agent.get(url).call().ok()?;
+            let content_type = resp.content_type().to_string();
+            if !content_type.starts_with("image/") || content_type.contains("svg") {
+                return None;
+            }
+            let mut data = Vec::new();
+            resp.into_reader()
+                .take(MAX_IMAGE_SIZE + 1)
+                .read_to_end(&mut data)
+                .ok()?;
+            if data.len() as u64 > MAX_IMAGE_SIZE {
+                return None;
+            }
+            let image = InlineImage {
+                cid: format!("{:016x}@the-daily-stallman", util::stable_hash(url)),
+                content_type,
+                data,
+            };
+            Some((url.clone(), image))
+        })
+        .collect::<Vec<_>>();
+
+    for img in imgs {
+        let mut attrs = img.attributes.borrow_mut();
+        let src = attrs.get("src").unwrap_or_default().to_string();
+        if let Some((_, image)) = images.iter().find(|(url, _)| *url == src) {
+            attrs.insert("src", format!("cid:{}", image.cid));
+            // Otherwise the mail client may still load the image from the web.
+            attrs.remove("srcset");
+        }
+    }
+
+    let images = images.into_iter().map(|(_, image)| image).collect();
+    (doc.to_string(), images)
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    let encoded = base64::encode(data);
+    encoded
+        .as_bytes()
+        .chunks(BASE64_LINE_LENGTH)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base64 is ascii"))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Encodes a header value as RFC 2047 encoded words if it isn't plain ASCII. The lines are at most
+/// 78 characters long, including the header name on the first line.
+fn encode_header(name: &str, value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return value.to_string();
+    }
+
+    // Encoded words may be at most 75 characters long, =?UTF-8?B? and ?= take 12 of them. The
+    // first line starts with the header name, folded lines with a space.
+    let max_bytes = |indent: usize| (75.min(78 - indent) - 12) / 4 * 3;
+    let mut limit = max_bytes(name.len() + 2);
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in value.chars() {
+        if word.len() + c.len_utf8() > limit {
+            words.push(std::mem::take(&mut word));
+            limit = max_bytes(1);
+        }
+        if !c.is_control() {
+            word.push(c);
+        }
+    }
+    words.push(word);
+
+    words
+        .iter()
+        .map(|word| format!("=?UTF-8?B?{}?=", base64::encode(word)))
+        .collect::<Vec<_>>()
+        .join("\r\n ")
+}
+
+/// The message as an entry in an mbox file (mboxrd format).
+fn mbox_entry(message: &str, from: &str, date: DateTime<Local>) -> String {
+    let mut entry = format!("From {} {}\n", from, date.format("%a %b %e %H:%M:%S %Y"));
+    for line in message.lines() {
+        if line.trim_start_matches('>').starts_with("From ") {
+            entry.push('>');
+        }
+        entry.push_str(line);
+        entry.push('\n');
+    }
+    entry.push('\n');
+    entry
+}
+
+/// Sends the message to an SMTP server that accepts mail without authentication or TLS, such as a
+/// local mail relay.
+fn smtp_send(server: &str, from: &str, to: &[String], message: &str) -> AnyResult<()> {
+    let stream = TcpStream::connect(server)?;
+    stream.set_read_timeout(Some(Duration::from_secs(60)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let mut command = |command: &str, expected: &[u16]| -> AnyResult<()> {
+        if !command.is_empty() {
+            writer.write_all(command.as_bytes())?;
+            writer.write_all(b"\r\n")?;
+        }
+        let (code, reply) = smtp_reply(&mut reader)?;
+        if !expected.contains(&code) {
+            bail!("unexpected SMTP reply: {} {}", code, reply);
+        }
+        Ok(())
+    };
+
+    command("", &[220])?;
+    command("EHLO localhost", &[250])?;
+    command(&format!("MAIL FROM:<{}>", from), &[250])?;
+    for to in to {
+        command(&format!("RCPT TO:<{}>", to), &[250, 251])?;
+    }
+    command("DATA", &[354])?;
+
+    let mut data = String::with_capacity(message.len());
+    for line in message.lines() {
+        // Lines starting with a dot are escaped since a single dot ends the data.
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push('.');
+    command(&data, &[250])?;
+    command("QUIT", &[221])?;
+    Ok(())
+}
+
+/// Reads a (possibly multiline) SMTP reply and returns its code and text.
+fn smtp_reply(reader: &mut impl BufRead) -> AnyResult<(u16, String)> {
+    let mut text = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("connection closed by SMTP server");
+        }
+        let line = line.trim_end();
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| anyhow!("invalid SMTP reply: {}", line))?;
+        text.push_str(line.get(4..).unwrap_or_default());
+        // "250-text" is followed by more lines, "250 text" is the last line.
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, text));
+        }
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn opts(transport: Transport) -> MailOpts {
+        MailOpts {
+            to: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+            from: "tds@example.com".to_string(),
+            subject: None,
+            transport,
+        }
+    }
+
+    fn decode_part(message: &str, content_type: &str) -> Vec<u8> {
+        let start = message
+            .find(&format!("Content-Type: {}", content_type))
+            .unwrap();
+        let body = &message[start..];
+        let body = &body[body.find("\r\n\r\n").unwrap() + 4..];
+        let body = &body[..body.find("\r\n--").unwrap()];
+        base64::decode(body.replace("\r\n", "")).unwrap()
+    }
+
+    #[test]
+    fn builds_multipart_message() {
+        let image = InlineImage {
+            cid: "1@tds".to_string(),
+            content_type: "image/png".to_string(),
+            data: vec![0x89, b'P', b'N', b'G'],
+        };
+        let html = "<p>Hällo <img src=\"cid:1@tds\"></p>".repeat(20);
+
+        let message = build(
+            &opts(Transport::File),
+            "Ünïcode subject",
+            Local::now(),
+            "Hällo",
+            &html,
+            &[image],
+        );
+
+        assert!(message.contains("To: a@example.com, b@example.com\r\n"));
+        assert!(message.contains("Subject: =?UTF-8?B?"));
+        assert!(message.contains("Content-Type: multipart/related; type="));
+        assert!(message.contains("Content-ID: <1@tds>\r\n"));
+        assert!(message
+            .lines()
+            .all(|line| line.len() <= 78 && line.is_ascii()));
+        assert_eq!(decode_part(&message, "text/plain"), "Hällo".as_bytes());
+        assert_eq!(decode_part(&message, "text/html"), html.as_bytes());
+        assert_eq!(decode_part(&message, "image/png"), b"\x89PNG");
+    }
+
+    #[test]
+    fn folds_long_subjects() {
+        let subject = "Ünïcode subject that is much longer than a single encoded word ".repeat(3);
+
+        let message = build(
+            &opts(Transport::File),
+            &subject,
+            Local::now(),
+            "Hällo",
+            "<p>Hällo</p>",
+            &[],
+        );
+
+        let header = &message[message.find("Subject: ").unwrap()..];
+        let header = &header[..header.find("\r\nDate: ").unwrap()];
+        assert!(header.lines().count() > 1);
+        assert!(header.lines().all(|line| line.len() <= 78));
+        let decoded = header
+            .split_whitespace()
+            .skip(1)
+            .map(|word| {
+                let word = word.trim_start_matches("=?UTF-8?B?").trim_end_matches("?=");
+                String::from_utf8(base64::decode(word).unwrap()).unwrap()
+            })
+            .collect::<String>();
+        assert_eq!(decoded, subject);
+    }
+
+    #[test]
+    fn escapes_mbox_from_lines() {
+        let entry = mbox_entry(
+            "Subject: x\r\n\r\nFrom here\r\n>From there\r\n",
+            "a@b",
+            Local::now(),
+        );
+        assert!(entry.starts_with("From a@b "));
+        assert!(entry.ends_with("\nSubject: x\n\n>From here\n>>From there\n\n"));
+    }
+
+    #[test]
+    fn validates_addresses() {
+        assert!(is_valid_address("rms@gnu.org"));
+        assert!(!is_valid_address("rms"));
+        assert!(!is_valid_address("@gnu.org"));
+        assert!(!is_valid_address("rms@gnu.org>\r\nRCPT TO:<x@y"));
+    }
+
+    #[test]
+    fn sends_with_smtp() {
+        // A minimal SMTP server that accepts one message.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut commands = Vec::new();
+            let mut data = String::new();
+            writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                let reply: &[u8] = match line.as_str() {
+                    l if l.starts_with("EHLO") => b"250-localhost\r\n250 8BITMIME\r\n",
+                    "DATA" => b"354 go ahead\r\n",
+                    "QUIT" => b"221 bye\r\n",
+                    _ => b"250 ok\r\n",
+                };
+                commands.push(line.clone());
+                writer.write_all(reply).unwrap();
+                if line == "DATA" {
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        data.push_str(&line);
+                    }
+                    writer.write_all(b"250 queued\r\n").unwrap();
+                }
+                if line == "QUIT" {
+                    return (commands, data);
+                }
+            }
+        });
+
+        let message = "Subject: x\r\n\r\n.leading dot\r\nbody\r\n";
+        deliver(message, &opts(Transport::Smtp(server)), Path::new("")).unwrap();
+        let (commands, data) = handle.join().unwrap();
+
+        assert_eq!(
+            commands,
+            vec![
+                "EHLO localhost",
+                "MAIL FROM:<tds@example.com>",
+                "RCPT TO:<a@example.com>",
+                "RCPT TO:<b@example.com>",
+                "DATA",
+                "QUIT"
+            ]
+        );
+        assert_eq!(data, "Subject: x\r\n\r\n..leading dot\r\nbody\r\n");
+    }
+}
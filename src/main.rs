@@ -2,29 +2,46 @@
 
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
 
+mod action;
+mod archive;
 mod convert;
+mod domain;
+mod export;
 mod extract;
 mod feed;
 mod filter;
+mod glossary;
+mod hook;
+mod links;
+mod logger;
+mod mail;
+mod metadata;
 mod options;
+mod progress;
+mod readability;
+mod report;
 mod resolve;
+mod search;
+mod tags;
+mod template;
 mod util;
+mod watch;
 
-use crate::options::{FetchType, Opts};
+use crate::archive::Archive;
+use crate::convert::Layout;
+use crate::glossary::Glossary;
+use crate::hook::RunSummary;
+use crate::options::{GlossaryOpts, Opts, OutputFormat, SearchOpts, Transport, WatchOpts};
+use crate::report::Report;
 use anyhow::Result as AnyResult;
 use anyhow::*;
-use chrono::{Duration, Local};
-use feed::Item;
+use resolve::ResolvedItem;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
 use std::{fs, process};
 
-lazy_static! {
-    static ref TEMP_FILE: PathBuf =
-        std::env::temp_dir().join("123679816239the-daily-stallman.html");
-}
-
 fn main() {
     if let Err(err) = run() {
         eprintln!("Error: {:?}\n", err);
@@ -33,76 +50,207 @@ fn main() {
 }
 
 fn run() -> AnyResult<()> {
-    remove_temp_file_if_exists();
     let mut opts = Opts::parse(std::env::args())?;
+    logger::init(opts.log_level);
+    opts.load_glossary();
+    util::remove_stale_temp_files(opts.temp_max_age);
+    // Load the layout first so that an invalid template is reported before fetching anything.
+    let layout = Layout::load(&opts)?;
 
-    if let Some(debug) = opts.debug.take() {
+    if let Some(command) = opts.glossary_command.take() {
+        glossary(&command, &mut opts.glossary)?;
+    } else if let Some(search) = opts.search.take() {
+        search_archive(&search, &layout)?;
+    } else if let Some(watch) = opts.watch.take() {
+        watch_feed(&watch, &opts, &layout)?;
+    } else if let Some(debug) = opts.debug.take() {
         let resolved = resolve::resolve_items(vec![debug]);
         if let Some(article) = resolved.first().and_then(|r| r.articles.first()) {
-            println!("{:#?}", article);
-            let html = convert::html(&resolved);
-            output_html(&html, &opts)?;
+            eprintln!("{:#?}", article);
+            let output = render(&resolved, &opts, &layout)?;
+            let path = write_output(&output, &opts)?;
+            finish(&resolved, path, &opts)?;
         }
     } else {
         let mut items = feed::items().context("failed to get items from RSS feed.")?;
-        filter_items(&mut items, &opts);
+        feed::filter_items(&mut items, &opts);
 
         if !items.is_empty() {
-            let resolved = resolve::resolve_items(items);
-            let html = convert::html(&resolved);
-            output_html(&html, &opts)?;
+            let resolved = if opts.links_only {
+                resolve::unresolved_items(items)
+            } else {
+                let resolved = resolve::resolve_items(items);
+                archive_items(&resolved);
+                resolved
+            };
+            hook::run_item_hooks(&opts.item_hooks, &resolved);
+            let output = render(&resolved, &opts, &layout)?;
+            let path = write_output(&output, &opts)?;
+            let report = finish(&resolved, path, &opts)?;
+
+            if opts.fail_on_errors && report.failed > 0 {
+                error!(
+                    "{} of {} articles could not be fetched",
+                    report.failed,
+                    report.ok + report.failed
+                );
+                process::exit(2);
+            }
         } else {
-            println!("No articles found. Try a different filter.")
+            info!("No articles found. Try a different filter.")
         }
     }
     Ok(())
 }
 
-fn remove_temp_file_if_exists() {
-    if TEMP_FILE.is_file() {
-        let _ = std::fs::remove_file(TEMP_FILE.as_path());
+fn glossary(opts: &GlossaryOpts, glossary: &mut Glossary) -> AnyResult<()> {
+    if opts.update || !opts.add.is_empty() {
+        glossary.update(&opts.add)?;
+    }
+    for entry in glossary.entries() {
+        println!(
+            "{}\n    {}\n    {}\n",
+            entry.term, entry.url, entry.definition
+        );
     }
+    Ok(())
 }
 
-fn filter_items(items: &mut Vec<Item>, opts: &Opts) {
-    match opts.fetch {
-        FetchType::Today | FetchType::Yesterday => {
-            let target_date = if let FetchType::Yesterday = opts.fetch {
-                Local::now() - Duration::days(1)
-            } else {
-                Local::now()
-            }
-            .date();
+fn search_archive(opts: &SearchOpts, layout: &Layout) -> AnyResult<()> {
+    let archive = Archive::open()?;
+    let results = search::search(&archive, &opts.query, opts.limit)?;
 
-            items.retain(|item| item.date.map(|d| d.date()) == Some(target_date));
-        }
-        FetchType::Latest(n) => {
-            let mut i = 0;
-            items.retain(|_| {
-                let keep = i < n;
-                i += 1;
-                keep
-            });
+    // The results are listed on stderr if the digest is written to stdout.
+    let to_stdout = opts.output_file.as_deref().is_some_and(util::is_stdout);
+    let list = |line: String| {
+        if to_stdout {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
         }
+    };
+
+    if results.is_empty() {
+        list(format!("No archived items match `{}`.", opts.query));
+        return Ok(());
+    }
+
+    for result in &results {
+        let item = &result.item.item;
+        let date = item
+            .date
+            .as_deref()
+            .map_or("", |d| d.get(..10).unwrap_or(d));
+        list(format!(
+            "{:10}  {}\n    {}\n",
+            date, item.title, result.snippet
+        ));
     }
+
+    if let Some(path) = opts.output_file.as_ref() {
+        let resolved = results
+            .into_iter()
+            .map(|result| result.item.item.into_resolved())
+            .collect::<Vec<_>>();
+        util::write_file(path, &convert::html(&resolved, layout))?;
+    }
+    Ok(())
 }
 
-fn output_html(html: &str, opts: &Opts) -> AnyResult<()> {
-    match (opts.output_file.as_ref(), opts.browser.as_ref()) {
-        (Some(output), _) => {
-            fs::write(&output, &html)?;
-        }
-        (_, Some(browser)) => {
-            fs::write(TEMP_FILE.as_path(), html)?;
-            Command::new(browser)
-                .arg(TEMP_FILE.as_path())
-                .stdout(Stdio::null())
-                .stdin(Stdio::null())
-                .spawn()?;
+fn watch_feed(watch: &WatchOpts, opts: &Opts, layout: &Layout) -> AnyResult<()> {
+    info!(
+        "Watching the feed, polling every {} s",
+        watch.interval.as_secs()
+    );
+    // All items fetched since the watch started, newest first.
+    let mut digest = Vec::new();
+
+    watch::watch(watch.interval, opts, |new_items| {
+        hook::run_item_hooks(&opts.item_hooks, &new_items);
+
+        // An email only contains the new items, other formats are rewritten with all items.
+        if opts.format == OutputFormat::Email {
+            let output = render(&new_items, opts, layout)?;
+            let path = write_output(&output, opts)?;
+            finish(&new_items, path, opts)?;
+        } else {
+            digest.splice(0..0, new_items);
+            let output = render(&digest, opts, layout)?;
+            let path = write_output(&output, opts)?;
+            finish(&digest, path, opts)?;
         }
-        (None, None) => {
-            fs::write("tds.html", &html)?;
+        Ok(())
+    })
+}
+
+/// Logs and writes the report of the run, then runs the post-run hooks with the written output.
+fn finish(resolved: &[ResolvedItem], output: Option<PathBuf>, opts: &Opts) -> AnyResult<Report> {
+    let report = Report::new(resolved);
+    report.log();
+    if let Some(path) = opts.report_file.as_ref() {
+        report.write(path).context("failed to write report")?;
+    }
+
+    hook::run_post_run_hooks(
+        &opts.post_run,
+        &RunSummary {
+            output: output.as_deref(),
+            format: opts.format.name(),
+            items: resolved.len(),
+            report: &report,
+        },
+    );
+    Ok(report)
+}
+
+// Archiving is best effort, a failure should not prevent the digest from being written.
+fn archive_items(resolved: &[ResolvedItem]) {
+    let result = Archive::open().and_then(|archive| {
+        let ids = archive.store(resolved)?;
+        search::update_index(&archive, &ids)?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        warn!("failed to archive items: {:#}", err);
+    }
+}
+
+fn render(resolved: &[ResolvedItem], opts: &Opts, layout: &Layout) -> AnyResult<String> {
+    match opts.format {
+        OutputFormat::Html => Ok(convert::html(resolved, layout)),
+        OutputFormat::Json => export::json(resolved),
+        OutputFormat::Email => {
+            let html = convert::html(resolved, layout);
+            let mail = opts.mail.as_ref().expect("mail options are set for email");
+            Ok(mail::message(resolved, &html, mail))
         }
     }
-    Ok(())
+}
+
+/// Writes or sends the output and returns the path of the written file, if it was written to one.
+fn write_output(output: &str, opts: &Opts) -> AnyResult<Option<PathBuf>> {
+    if let Some(mail) = opts.mail.as_ref() {
+        let path = opts
+            .output_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(opts.format.default_file_name()));
+        mail::deliver(output, mail, &path)?;
+        return Ok(match mail.transport {
+            Transport::File => Some(path),
+            Transport::Sendmail(_) | Transport::Smtp(_) => None,
+        });
+    }
+
+    let path = match (opts.output_file.as_ref(), opts.browser.as_ref()) {
+        (Some(path), _) if util::is_stdout(path) => {
+            util::write_file(path, output)?;
+            return Ok(None);
+        }
+        (Some(path), _) => path.clone(),
+        // The browser is started by its post-run hook.
+        (_, Some(_)) => util::temp_file("html"),
+        (None, None) => PathBuf::from(opts.format.default_file_name()),
+    };
+    fs::write(&path, output)?;
+    Ok(Some(path))
 }
@@ -0,0 +1,209 @@
+use crate::util;
+use extrablatt::select::document::Document;
+use extrablatt::select::predicate::{Attr, Name, Predicate};
+use serde_json::Value;
+
+/// Article metadata published by the site itself, either as schema.org JSON-LD or as
+/// OpenGraph/Twitter meta tags. JSON-LD takes precedence over meta tags.
+#[derive(Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    /// The publishing date as found in the metadata.
+    pub publishing_date: Option<String>,
+    pub description: Option<String>,
+    /// The URL of the lead image.
+    pub image: Option<String>,
+    pub publisher: Option<String>,
+    pub section: Option<String>,
+}
+
+/// JSON-LD types that describe an article.
+const ARTICLE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "ReportageNewsArticle",
+    "AnalysisNewsArticle",
+    "OpinionNewsArticle",
+    "BackgroundNewsArticle",
+    "BlogPosting",
+    "Report",
+    "ScholarlyArticle",
+];
+
+pub fn extract(doc: &Document) -> Metadata {
+    let mut metadata = json_ld(doc).unwrap_or_default();
+    let meta = |names: &[&str]| names.iter().find_map(|name| meta_content(doc, name));
+
+    metadata.title = metadata
+        .title
+        .or_else(|| meta(&["og:title", "twitter:title"]));
+    if metadata.authors.is_empty() {
+        // article:author is often a link to a profile page rather than a name.
+        metadata.authors = meta(&["author", "article:author", "parsely-author"])
+            .filter(|author| !util::is_http(author))
+            .into_iter()
+            .collect();
+    }
+    metadata.publishing_date = metadata.publishing_date.or_else(|| {
+        meta(&[
+            "article:published_time",
+            "datePublished",
+            "shareaholic:article_published_time",
+            "parsely-pub-date",
+            "pubdate",
+        ])
+    });
+    metadata.description = metadata
+        .description
+        .or_else(|| meta(&["og:description", "twitter:description", "description"]));
+    metadata.image = metadata
+        .image
+        .or_else(|| meta(&["og:image", "twitter:image", "twitter:image:src"]));
+    metadata.publisher = metadata.publisher.or_else(|| meta(&["og:site_name"]));
+    metadata.section = metadata.section.or_else(|| meta(&["article:section"]));
+
+    metadata
+}
+
+/// Returns the content of the first meta tag with the name, property or itemprop `name`.
+fn meta_content(doc: &Document, name: &str) -> Option<String> {
+    doc.find(
+        Name("meta").and(
+            Attr("property", name)
+                .or(Attr("name", name))
+                .or(Attr("itemprop", name)),
+        ),
+    )
+    .filter_map(|node| node.attr("content"))
+    .map(str::trim)
+    .find(|content| !content.is_empty())
+    .map(str::to_string)
+}
+
+fn json_ld(doc: &Document) -> Option<Metadata> {
+    let article = doc
+        .find(Name("script").and(Attr("type", "application/ld+json")))
+        .filter_map(|script| serde_json::from_str::<Value>(&script.text()).ok())
+        .find_map(|json| find_article(&json).cloned())?;
+
+    Some(Metadata {
+        title: string(&article["headline"]).or_else(|| string(&article["name"])),
+        authors: names(&article["author"]),
+        publishing_date: string(&article["datePublished"]),
+        description: string(&article["description"]),
+        image: url(&article["image"]),
+        publisher: names(&article["publisher"]).into_iter().next(),
+        section: match &article["articleSection"] {
+            Value::Array(sections) => sections.iter().find_map(string),
+            section => string(section),
+        },
+    })
+}
+
+/// Finds the first article object in a JSON-LD document. The document can be a single object,
+/// an array of objects or an object with a @graph array.
+fn find_article(json: &Value) -> Option<&Value> {
+    match json {
+        Value::Array(values) => values.iter().find_map(find_article),
+        Value::Object(object) => {
+            let is_article = match &json["@type"] {
+                Value::String(t) => ARTICLE_TYPES.contains(&t.as_str()),
+                Value::Array(types) => types
+                    .iter()
+                    .any(|t| ARTICLE_TYPES.contains(&t.as_str().unwrap_or_default())),
+                _ => false,
+            };
+            if is_article {
+                Some(json)
+            } else {
+                object.get("@graph").and_then(find_article)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Names of persons or organizations, given as strings, objects with a name or arrays of either.
+fn names(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(values) => values.iter().flat_map(names).collect(),
+        Value::Object(_) => string(&value["name"]).into_iter().collect(),
+        _ => string(value).into_iter().collect(),
+    }
+}
+
+/// An image URL, given as a string, an ImageObject or an array of either.
+fn url(value: &Value) -> Option<String> {
+    match value {
+        Value::Array(values) => values.iter().find_map(url),
+        Value::Object(_) => string(&value["url"]).or_else(|| string(&value["contentUrl"])),
+        _ => string(value),
+    }
+}
+
+fn string(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_json_ld_article() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+                {"@type": "WebSite", "name": "Site"},
+                {"@type": ["NewsArticle"], "headline": "Headline",
+                 "author": [{"@type": "Person", "name": "A"}, "B"],
+                 "datePublished": "2020-11-22T23:24:42-05:00",
+                 "image": {"@type": "ImageObject", "url": "https://example.com/a.jpg"},
+                 "publisher": {"@type": "Organization", "name": "Publisher"},
+                 "articleSection": ["World", "Health"]}
+            ]}
+            </script>
+            <meta property="og:title" content="OpenGraph title">
+            <meta property="og:description" content="Description">
+        </head><body></body></html>"#;
+
+        assert_eq!(
+            extract(&Document::from(html)),
+            Metadata {
+                title: Some("Headline".to_string()),
+                authors: vec!["A".to_string(), "B".to_string()],
+                publishing_date: Some("2020-11-22T23:24:42-05:00".to_string()),
+                description: Some("Description".to_string()),
+                image: Some("https://example.com/a.jpg".to_string()),
+                publisher: Some("Publisher".to_string()),
+                section: Some("World".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn extracts_meta_tags() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">not json</script>
+            <meta property="og:title" content="Title">
+            <meta name="twitter:image" content="https://example.com/a.jpg">
+            <meta property="article:author" content="https://example.com/author">
+            <meta property="article:published_time" content="2020-11-22">
+            <meta property="og:site_name" content="Site">
+        </head><body></body></html>"#;
+
+        let metadata = extract(&Document::from(html));
+
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert!(metadata.authors.is_empty());
+        assert_eq!(metadata.publishing_date.as_deref(), Some("2020-11-22"));
+        assert_eq!(metadata.image.as_deref(), Some("https://example.com/a.jpg"));
+        assert_eq!(metadata.publisher.as_deref(), Some("Site"));
+        assert_eq!(metadata.section, None);
+    }
+}
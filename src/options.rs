@@ -1,16 +1,144 @@
-use crate::feed::Item;
+use crate::domain::DomainFilter;
+use crate::feed::{Item, ItemFilter};
+use crate::glossary::Glossary;
+use crate::hook::PostRunHook;
+use crate::logger;
+use crate::mail;
+use crate::tags::Tagger;
+use crate::util;
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result as AnyResult;
-use clap::{App, Arg, ArgMatches};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log::LevelFilter;
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const MIN_WATCH_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug)]
 pub struct Opts {
     pub output_file: Option<PathBuf>,
+    /// The browser the output is opened in, from --browser or --open.
     pub browser: Option<PathBuf>,
+    /// Temp files from earlier runs that are older than this are removed.
+    pub temp_max_age: Duration,
     pub fetch: FetchType,
+    /// Only render RMS's comments and the links, without fetching the articles.
+    pub links_only: bool,
+    /// End every article in the HTML output with a list of its links.
+    pub references: bool,
+    /// Selects items by --match and --exclude patterns.
+    pub item_filter: ItemFilter,
+    /// Selects article links by domain.
+    pub domain_filter: DomainFilter,
+    /// Tags the items with topics.
+    pub tagger: Tagger,
+    /// Group the HTML output by tag.
+    pub group_by_tag: bool,
+    pub format: OutputFormat,
+    /// A user HTML template replacing the built-in one.
+    pub template: Option<PathBuf>,
+    pub theme: Theme,
+    /// A user CSS file replacing the theme CSS.
+    pub css: Option<PathBuf>,
+    /// Lay out the HTML output for printing.
+    pub print: bool,
+    /// Set if the output format is email.
+    pub mail: Option<MailOpts>,
     pub debug: Option<Item>,
+    pub log_level: LevelFilter,
+    /// If set, the run report is written to this file as JSON.
+    pub report_file: Option<PathBuf>,
+    /// Exit with status 2 if any article could not be fetched.
+    pub fail_on_errors: bool,
+    /// Run in order after the output is written. Opening the output in the browser is one of them.
+    pub post_run: Vec<PostRunHook>,
+    /// Shell commands that are run for every fetched item.
+    pub item_hooks: Vec<String>,
+    /// The glossary of the terms RMS links to. These links are not fetched as articles. Only the
+    /// built-in glossary until `load_glossary` is called.
+    pub glossary: Glossary,
+    pub search: Option<SearchOpts>,
+    pub watch: Option<WatchOpts>,
+    pub glossary_command: Option<GlossaryOpts>,
+}
+
+#[derive(Debug)]
+pub struct GlossaryOpts {
+    /// Download the definitions again.
+    pub update: bool,
+    /// Glossary pages to add.
+    pub add: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct WatchOpts {
+    /// How often the feed is polled.
+    pub interval: Duration,
+}
+
+#[derive(Debug)]
+pub struct SearchOpts {
+    pub query: String,
+    pub limit: usize,
+    /// If set, the matching items are rendered as a digest to this file.
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct MailOpts {
+    pub to: Vec<String>,
+    pub from: String,
+    /// The subject, or None for the default subject containing the date.
+    pub subject: Option<String>,
+    pub transport: Transport,
+}
+
+/// How the digest email is delivered.
+#[derive(Debug)]
+pub enum Transport {
+    /// Written to the output file.
+    File,
+    Sendmail(PathBuf),
+    /// The host:port of an SMTP server.
+    Smtp(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    Html,
+    Json,
+    Email,
+}
+
+impl OutputFormat {
+    /// The file name used if no output file name is given.
+    pub fn default_file_name(self) -> &'static str {
+        match self {
+            OutputFormat::Html => "tds.html",
+            OutputFormat::Json => "tds.json",
+            OutputFormat::Email => "tds.eml",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Html => "html",
+            OutputFormat::Json => "json",
+            OutputFormat::Email => "email",
+        }
+    }
+}
+
+/// The built-in themes for the HTML output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Theme {
+    Default,
+    Dark,
+    HighContrast,
+    Print,
 }
 
 #[derive(Debug)]
@@ -37,7 +165,7 @@ impl Opts {
                     .help(
                         "A path (relative or absolute) to where the HTML output is written to. \
                         If the path is an existing directory, the file is placed in the directory \
-                        and named tds.html",
+                        and named tds.html (or tds.json). Use - to write to stdout.",
                     ),
             )
             .arg(
@@ -49,8 +177,115 @@ impl Opts {
                     .help(
                         "The name of a browser executable to open the HTML output in. If this \
                         option is set, the output is written to a temporary file that is opened \
-                        automatically in the browser. The temp file is removed (or replaced) the \
-                        next time `tds` is run, or when the system temp file cleaner removes it.",
+                        automatically in the browser. Temp files are removed by a later run of \
+                        `tds` when they are older than --temp-max-age.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("open")
+                    .long("open")
+                    .conflicts_with_all(&["output", "browser"])
+                    .help(
+                        "Opens the HTML output in the default browser, like --browser. The \
+                        browser in the BROWSER environment variable is used if it is set, \
+                        otherwise the desktop default (xdg-open, or open on macOS).",
+                    ),
+            )
+            .arg(
+                Arg::with_name("temp-max-age")
+                    .long("temp-max-age")
+                    .takes_value(true)
+                    .help(
+                        "Temp files written for the browser by earlier runs are removed when \
+                        they are older than this, e.g. 30m or 2h. Defaults to 1d.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .long("format")
+                    .takes_value(true)
+                    .possible_values(&["html", "json", "email"])
+                    .help(
+                        "The output format. Defaults to html. The json format contains all feed \
+                        items and extracted articles and is described in the README. The email \
+                        format is a MIME message with HTML and plain text versions of the digest \
+                        that is written to a file (.eml, or appended to an mbox file if the file \
+                        name ends in .mbox) or sent with --sendmail or --smtp.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("mail-to")
+                    .long("mail-to")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required_if("format", "email")
+                    .help("A recipient of the email. Can be given multiple times."),
+            )
+            .arg(
+                Arg::with_name("mail-from")
+                    .long("mail-from")
+                    .takes_value(true)
+                    .help("The sender of the email. Defaults to the-daily-stallman@localhost."),
+            )
+            .arg(
+                Arg::with_name("mail-subject")
+                    .long("mail-subject")
+                    .takes_value(true)
+                    .help("The subject of the email. Defaults to the digest date."),
+            )
+            .arg(
+                Arg::with_name("sendmail")
+                    .long("sendmail")
+                    .takes_value(true)
+                    .conflicts_with("output")
+                    .help(
+                        "Sends the email with this sendmail compatible program, e.g. sendmail or \
+                        msmtp.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("smtp")
+                    .long("smtp")
+                    .takes_value(true)
+                    .conflicts_with_all(&["output", "sendmail"])
+                    .help(
+                        "Sends the email to this SMTP server (host:port). The server must accept \
+                        mail without authentication or TLS, like a local mail relay.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("template")
+                    .long("template")
+                    .takes_value(true)
+                    .help(
+                        "A path to an HTML template used instead of the built-in one. The \
+                        template syntax and variables are described in the README.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("theme")
+                    .long("theme")
+                    .takes_value(true)
+                    .possible_values(&["default", "dark", "high-contrast", "print"])
+                    .help("The built-in theme used for the HTML output. Defaults to default."),
+            )
+            .arg(
+                Arg::with_name("css")
+                    .long("css")
+                    .takes_value(true)
+                    .conflicts_with("theme")
+                    .help("A path to a CSS file used instead of the built-in theme."),
+            )
+            .arg(
+                Arg::with_name("print")
+                    .long("print")
+                    .conflicts_with("template")
+                    .help(
+                        "Lays out the HTML output for printing or saving as PDF from the browser. \
+                        Every feed item starts on a new page with a header showing the digest \
+                        date, and links are numbered with their URLs listed below each item. \
+                        Uses the print theme unless --theme or --css is given.",
                     ),
             )
             .arg(
@@ -72,31 +307,313 @@ impl Opts {
                     .conflicts_with("yesterday")
                     .help("Fetches the latest N articles from the feed."),
             )
+            .arg(
+                Arg::with_name("no-fetch")
+                    .long("no-fetch")
+                    .visible_alias("links-only")
+                    .help(
+                        "Does not fetch the articles, the output only contains RMS's comments and \
+                        the article links. This is much faster and works offline if the feed is \
+                        reachable. The items are not added to the archive.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("match")
+                    .long("match")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "Only fetches items whose title or description (including link texts) \
+                        match this regular expression, case insensitively. Can be given multiple \
+                        times to fetch items matching any of the patterns, or all of them with \
+                        --match-all. Applied before --today, --yesterday and --latest.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("match-all")
+                    .long("match-all")
+                    .requires("match")
+                    .help("Items must match all --match patterns instead of any of them."),
+            )
+            .arg(
+                Arg::with_name("exclude")
+                    .long("exclude")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "Skips items whose title or description match this regular expression, \
+                        case insensitively. Can be given multiple times.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("tag")
+                    .long("tag")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "Only fetches items tagged with this topic, e.g. patents or surveillance. \
+                        Can be given multiple times to fetch items with any of the tags. Items are \
+                        tagged by keywords in the title and description, see --tag-dictionary.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("tag-dictionary")
+                    .long("tag-dictionary")
+                    .takes_value(true)
+                    .help(
+                        "A JSON file with the tags and their keywords, replacing the built-in \
+                        dictionary. See the README for the format.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("references")
+                    .long("references")
+                    .help(
+                        "Ends every article in the HTML output with a References section listing \
+                        the links in the article. Navigation, sharing and ad links are left out.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("group-by-tag")
+                    .long("group-by-tag")
+                    .help(
+                        "Groups the items in the HTML output by their first tag, with an index of \
+                        the tags at the top.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("allow-domain")
+                    .long("allow-domain")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "Only fetches articles from this domain and its subdomains. Can be given \
+                        multiple times. A * matches any part of a domain name, e.g. *.example.com \
+                        or example.*.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("deny-domain")
+                    .long("deny-domain")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "Never fetches articles from this domain and its subdomains, even if they \
+                        are allowed. Can be given multiple times and supports * like \
+                        --allow-domain. Links to stallman.org are always skipped. Skipped links \
+                        are logged with -v.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("report")
+                    .long("report")
+                    .takes_value(true)
+                    .help(
+                        "Writes a JSON report with the status, extractor and error of every link \
+                        to this path. The report is always logged at the end of the run.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("fail-on-errors")
+                    .long("fail-on-errors")
+                    .help(
+                        "Exits with status 2 if any article could not be fetched. The output is \
+                        still written.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("post-run")
+                    .long("post-run")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "A shell command that is run after the output is written. Can be given \
+                        multiple times. The output path is passed as $1 and in TDS_OUTPUT, and a \
+                        JSON summary of the run (the report plus output, format and items) on \
+                        stdin. TDS_FORMAT, TDS_ITEMS, TDS_ARTICLES_OK and TDS_ARTICLES_FAILED are \
+                        also set.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("item-hook")
+                    .long("item-hook")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help(
+                        "A shell command that is run for every fetched item. Can be given multiple \
+                        times. The item is passed as JSON on stdin and in the TDS_ITEM_ID, \
+                        TDS_ITEM_TITLE, TDS_ITEM_DATE, TDS_ITEM_LINKS, TDS_ITEM_TAGS, \
+                        TDS_ARTICLES_OK and TDS_ARTICLES_FAILED environment variables.",
+                    ),
+            )
+            .arg(
+                Arg::with_name("verbose")
+                    .short("v")
+                    .long("verbose")
+                    .multiple(true)
+                    .global(true)
+                    .help(
+                        "Logs more details, such as download times and sizes. Use -vv to also \
+                        log messages from libraries. The log level can also be set with the \
+                        TDS_LOG environment variable (off, error, warn, info, debug, trace).",
+                    ),
+            )
+            .arg(
+                Arg::with_name("quiet")
+                    .short("q")
+                    .long("quiet")
+                    .conflicts_with("verbose")
+                    .global(true)
+                    .help("Only logs errors."),
+            )
             .arg(
                 Arg::with_name("debug")
                     .long("debug")
                     .takes_value(true)
                     .help("Prints extraction information given an article URL."),
             )
+            .subcommand(
+                SubCommand::with_name("watch")
+                    .about(
+                        "Polls the feed periodically and fetches new items as they are added. \
+                        New items are archived and the output is rewritten with all items fetched \
+                        since the watch started (with --format email, an email with the new items \
+                        is sent). Options such as --output and --format are given before `watch`.",
+                    )
+                    .arg(
+                        Arg::with_name("interval")
+                            .long("interval")
+                            .takes_value(true)
+                            .help("How often to poll the feed, e.g. 30m or 1h. Defaults to 30m."),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("search")
+                    .about(
+                        "Searches all previously fetched items and articles. Every run of `tds` \
+                        adds the fetched items to a local archive that is searched.",
+                    )
+                    .arg(
+                        Arg::with_name("query")
+                            .required(true)
+                            .help("The words to search for. All words must match."),
+                    )
+                    .arg(
+                        Arg::with_name("limit")
+                            .long("limit")
+                            .short("l")
+                            .takes_value(true)
+                            .help("The maximum number of results. Defaults to 20."),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .help(
+                                "Renders the matching items as an HTML digest to this path. Use - \
+                                to write to stdout.",
+                            ),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("glossary")
+                    .about(
+                        "Lists the glossary of terms that RMS links to, such as \
+                        gnu.org/philosophy/not-ipr.html. These links are not fetched as articles, \
+                        their definitions are shown as notes after RMS's comment instead.",
+                    )
+                    .arg(Arg::with_name("update").long("update").help(
+                        "Downloads the definitions of all glossary pages again and saves the \
+                        glossary in the data directory.",
+                    ))
+                    .arg(
+                        Arg::with_name("add")
+                            .long("add")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help(
+                                "Adds the page at this URL to the glossary. A link to a section \
+                                of a page (like words-to-avoid.html#Piracy) adds the section.",
+                            ),
+                    ),
+            )
             .get_matches_from(iter);
 
+        let format = format(&matches);
+        if format == OutputFormat::Json && matches.is_present("print") {
+            bail!("--print can not be used with --format json");
+        }
+        let browser = browser(&matches, format)?;
+
         Ok(Opts {
-            output_file: output_file(&matches)?,
-            browser: browser(&matches)?,
+            output_file: output_file(&matches, format)?,
+            post_run: post_run(&matches, browser.as_ref()),
+            item_hooks: values(&matches, "item-hook"),
+            browser,
+            temp_max_age: util::parse_duration(matches.value_of("temp-max-age").unwrap_or("1d"))?,
             fetch: fetch(&matches)?,
+            links_only: matches.is_present("no-fetch"),
+            references: matches.is_present("references"),
+            item_filter: ItemFilter::new(
+                &values(&matches, "match"),
+                &values(&matches, "exclude"),
+                matches.is_present("match-all"),
+                &values(&matches, "tag"),
+            )?,
+            tagger: Tagger::load(matches.value_of("tag-dictionary").map(Path::new))?,
+            group_by_tag: matches.is_present("group-by-tag"),
+            domain_filter: DomainFilter::new(
+                &values(&matches, "allow-domain"),
+                &values(&matches, "deny-domain"),
+            )?,
+            format,
+            template: matches.value_of("template").map(PathBuf::from),
+            theme: theme(&matches),
+            print: matches.is_present("print"),
+            css: matches.value_of("css").map(PathBuf::from),
+            mail: mail(&matches, format)?,
             debug: debug(&matches),
+            report_file: matches.value_of("report").map(PathBuf::from),
+            fail_on_errors: matches.is_present("fail-on-errors"),
+            log_level: logger::level(
+                matches.occurrences_of("verbose"),
+                matches.is_present("quiet"),
+                std::env::var(logger::LOG_ENV).ok().as_deref(),
+            )?,
+            glossary: Glossary::default(),
+            search: search(&matches)?,
+            watch: watch(&matches)?,
+            glossary_command: glossary_command(&matches),
         })
     }
+
+    /// Loads the cached glossary. It is loaded after parsing, when the logger is set up, so that a
+    /// broken cache only gives a warning.
+    pub fn load_glossary(&mut self) {
+        self.glossary = Glossary::load();
+    }
 }
 
-fn output_file(matches: &ArgMatches) -> AnyResult<Option<PathBuf>> {
+fn output_file(matches: &ArgMatches, format: OutputFormat) -> AnyResult<Option<PathBuf>> {
     if let Some(output) = matches.value_of("output") {
+        if output == util::STDOUT {
+            return Ok(Some(PathBuf::from(output)));
+        }
         let mut path = output.parse::<PathBuf>()?;
         if path.is_relative() {
             path = std::env::current_dir()?.join(path);
         }
         path = if path.is_dir() {
-            path.join("tds.html")
+            path.join(format.default_file_name())
         } else {
             path
         };
@@ -106,14 +623,65 @@ fn output_file(matches: &ArgMatches) -> AnyResult<Option<PathBuf>> {
     }
 }
 
-fn browser(matches: &ArgMatches) -> AnyResult<Option<PathBuf>> {
-    if let Some(browser) = matches.value_of("browser") {
-        let exe =
-            which::which(browser).with_context(|| format!("is `{}` in your PATH?", browser))?;
-        Ok(Some(exe))
-    } else {
-        Ok(None)
+fn browser(matches: &ArgMatches, format: OutputFormat) -> AnyResult<Option<PathBuf>> {
+    let opens = matches.is_present("browser") || matches.is_present("open");
+    if opens && format != OutputFormat::Html {
+        bail!(
+            "--browser and --open can not be used with --format {}",
+            format.name()
+        );
     }
+    let browser = if let Some(browser) = matches.value_of("browser") {
+        browser.to_string()
+    } else if matches.is_present("open") {
+        default_browser()
+    } else {
+        return Ok(None);
+    };
+    let exe = which::which(&browser).with_context(|| format!("is `{}` in your PATH?", browser))?;
+    Ok(Some(exe))
+}
+
+/// The browser used by --open. BROWSER may be a list of browsers separated by colons, the first is
+/// used. Browsers may be given with arguments, like `firefox %s`; only the program is used since
+/// the output path is passed as the only argument.
+fn default_browser() -> String {
+    std::env::var("BROWSER")
+        .ok()
+        .and_then(|browsers| {
+            browsers
+                .split(':')
+                .find_map(|browser| browser.split_whitespace().next())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "macos") {
+                "open".to_string()
+            } else if cfg!(windows) {
+                "explorer".to_string()
+            } else {
+                "xdg-open".to_string()
+            }
+        })
+}
+
+fn post_run(matches: &ArgMatches, browser: Option<&PathBuf>) -> Vec<PostRunHook> {
+    browser
+        .map(|browser| PostRunHook::Open(browser.clone()))
+        .into_iter()
+        .chain(
+            values(matches, "post-run")
+                .into_iter()
+                .map(PostRunHook::Shell),
+        )
+        .collect()
+}
+
+fn values(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches
+        .values_of(name)
+        .map(|values| values.map(str::to_string).collect())
+        .unwrap_or_default()
 }
 
 fn fetch(matches: &ArgMatches) -> AnyResult<FetchType> {
@@ -127,15 +695,111 @@ fn fetch(matches: &ArgMatches) -> AnyResult<FetchType> {
     }
 }
 
-fn debug(matches: &ArgMatches) -> Option<Item> {
-    if let Some(url) = matches.value_of("debug") {
-        Some(Item {
-            title: "".to_string(),
-            date: None,
-            description: "".to_string(),
-            links: vec![url.to_string()],
+fn format(matches: &ArgMatches) -> OutputFormat {
+    match matches.value_of("format") {
+        Some("json") => OutputFormat::Json,
+        Some("email") => OutputFormat::Email,
+        _ => OutputFormat::Html,
+    }
+}
+
+fn theme(matches: &ArgMatches) -> Theme {
+    match matches.value_of("theme") {
+        Some("dark") => Theme::Dark,
+        Some("high-contrast") => Theme::HighContrast,
+        Some("print") => Theme::Print,
+        Some(_) => Theme::Default,
+        None if matches.is_present("print") => Theme::Print,
+        None => Theme::Default,
+    }
+}
+
+fn mail(matches: &ArgMatches, format: OutputFormat) -> AnyResult<Option<MailOpts>> {
+    let mail_args = ["mail-to", "mail-from", "mail-subject", "sendmail", "smtp"];
+    if format != OutputFormat::Email {
+        if let Some(arg) = mail_args.iter().find(|arg| matches.is_present(arg)) {
+            bail!("--{} can only be used with --format email", arg);
+        }
+        return Ok(None);
+    }
+
+    let to = values(matches, "mail-to");
+    let from = matches
+        .value_of("mail-from")
+        .unwrap_or("the-daily-stallman@localhost")
+        .to_string();
+    if let Some(address) = to
+        .iter()
+        .chain(Some(&from))
+        .find(|a| !mail::is_valid_address(a))
+    {
+        bail!("invalid email address `{}`", address);
+    }
+
+    let transport = if let Some(sendmail) = matches.value_of("sendmail") {
+        let exe =
+            which::which(sendmail).with_context(|| format!("is `{}` in your PATH?", sendmail))?;
+        Transport::Sendmail(exe)
+    } else if let Some(server) = matches.value_of("smtp") {
+        Transport::Smtp(server.to_string())
+    } else {
+        Transport::File
+    };
+
+    Ok(Some(MailOpts {
+        to,
+        from,
+        subject: matches.value_of("mail-subject").map(str::to_string),
+        transport,
+    }))
+}
+
+fn watch(matches: &ArgMatches) -> AnyResult<Option<WatchOpts>> {
+    if let Some(watch_matches) = matches.subcommand_matches("watch") {
+        if matches.is_present("browser") || matches.is_present("open") {
+            bail!("--browser and --open can not be used with watch");
+        }
+        if matches.is_present("no-fetch") {
+            bail!("--no-fetch can not be used with watch");
+        }
+        let interval = util::parse_duration(watch_matches.value_of("interval").unwrap_or("30m"))?;
+        // Be polite to stallman.org.
+        if interval < MIN_WATCH_INTERVAL {
+            bail!("the watch interval must be at least one minute");
+        }
+        Ok(Some(WatchOpts { interval }))
+    } else {
+        Ok(None)
+    }
+}
+
+fn glossary_command(matches: &ArgMatches) -> Option<GlossaryOpts> {
+    matches
+        .subcommand_matches("glossary")
+        .map(|matches| GlossaryOpts {
+            update: matches.is_present("update"),
+            add: values(matches, "add"),
         })
+}
+
+fn search(matches: &ArgMatches) -> AnyResult<Option<SearchOpts>> {
+    if let Some(matches) = matches.subcommand_matches("search") {
+        Ok(Some(SearchOpts {
+            query: matches.value_of("query").unwrap_or_default().to_string(),
+            limit: matches.value_of("limit").unwrap_or("20").parse()?,
+            output_file: output_file(matches, OutputFormat::Html)?,
+        }))
     } else {
-        None
+        Ok(None)
     }
 }
+
+fn debug(matches: &ArgMatches) -> Option<Item> {
+    matches.value_of("debug").map(|url| Item {
+        title: "".to_string(),
+        date: None,
+        description: "".to_string(),
+        links: vec![url.to_string()],
+        tags: vec![],
+    })
+}
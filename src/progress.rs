@@ -0,0 +1,224 @@
+use log::LevelFilter;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Progress of the article downloads. When stderr is a terminal, a status block with the number of
+// finished links and the links currently being fetched is drawn at the bottom of the terminal and
+// log messages are printed above it. Otherwise each finished link is logged on its own line.
+
+/// The maximum number of in-flight links shown in the status block.
+const MAX_IN_FLIGHT_LINES: usize = 5;
+
+/// Lines in the status block are cut to this length so that they don't wrap.
+const MAX_LINE_LENGTH: usize = 78;
+
+const BAR_WIDTH: usize = 30;
+
+lazy_static! {
+    static ref STATUS: Mutex<Option<Status>> = Mutex::new(None);
+}
+
+struct Status {
+    total: usize,
+    done: usize,
+    failed: Vec<String>,
+    in_flight: Vec<String>,
+    started: Instant,
+    slowest: Option<(String, Duration)>,
+    /// Whether the status block is drawn, i.e. stderr is a terminal.
+    live: bool,
+    /// The number of lines of the status block currently on screen.
+    drawn: usize,
+}
+
+/// Starts showing progress for fetching `total` links.
+pub fn start(total: usize) {
+    let live = atty::is(atty::Stream::Stderr)
+        && log::max_level() >= LevelFilter::Info
+        && std::env::var_os("TERM").is_none_or(|term| term != "dumb");
+
+    let mut status = STATUS.lock().unwrap();
+    *status = Some(Status {
+        total,
+        done: 0,
+        failed: Vec::new(),
+        in_flight: Vec::new(),
+        started: Instant::now(),
+        slowest: None,
+        live,
+        drawn: 0,
+    });
+    if let Some(status) = status.as_mut() {
+        status.draw();
+    }
+}
+
+pub fn link_started(link: &str) {
+    let mut status = STATUS.lock().unwrap();
+    if let Some(status) = status.as_mut() {
+        status.in_flight.push(link.to_string());
+        status.draw();
+    }
+}
+
+/// Records that fetching the link finished after `elapsed`, with an error message if it failed.
+pub fn link_finished(link: &str, elapsed: Duration, error: Option<&str>) {
+    let (live, done, total) = {
+        let mut status = STATUS.lock().unwrap();
+        let status = match status.as_mut() {
+            Some(status) => status,
+            None => return,
+        };
+        status.in_flight.retain(|l| l != link);
+        status.done += 1;
+        if error.is_some() {
+            status.failed.push(link.to_string());
+        }
+        if status.slowest.as_ref().is_none_or(|(_, d)| elapsed > *d) {
+            status.slowest = Some((link.to_string(), elapsed));
+        }
+        status.draw();
+        (status.live, status.done, status.total)
+    };
+
+    // Logged after the lock is released since the logger draws the status block again.
+    match error {
+        Some(error) => warn!("{} ... Error: {} - skipping article", link, error),
+        None if !live => info!("[{}/{}] {} ... Ok", done, total, link),
+        None => {}
+    }
+}
+
+/// Stops showing progress and logs a summary.
+pub fn finish() {
+    let status = {
+        let mut status = STATUS.lock().unwrap();
+        if let Some(status) = status.as_mut() {
+            status.clear();
+        }
+        status.take()
+    };
+
+    if let Some(status) = status {
+        info!("{}", status.summary());
+        if let Some((link, elapsed)) = &status.slowest {
+            debug!("slowest link: {} ({:.1} s)", link, elapsed.as_secs_f64());
+        }
+    }
+}
+
+/// Prints a message above the status block, if it is shown.
+pub fn print_above(print: impl FnOnce()) {
+    let mut status = STATUS.lock().unwrap();
+    match status.as_mut() {
+        Some(status) if status.live => {
+            status.clear();
+            print();
+            status.draw();
+        }
+        _ => print(),
+    }
+}
+
+impl Status {
+    fn draw(&mut self) {
+        if !self.live {
+            return;
+        }
+        self.clear();
+        let lines = self.lines();
+        let mut stderr = std::io::stderr();
+        for line in &lines {
+            let _ = writeln!(stderr, "{}", line);
+        }
+        let _ = stderr.flush();
+        self.drawn = lines.len();
+    }
+
+    /// Removes the status block from the screen.
+    fn clear(&mut self) {
+        if self.drawn > 0 {
+            // Move to the start of the first line of the block and clear to the end of screen.
+            eprint!("\x1b[{}F\x1b[J", self.drawn);
+            self.drawn = 0;
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let filled = (BAR_WIDTH * self.done)
+            .checked_div(self.total)
+            .unwrap_or(BAR_WIDTH);
+        let mut lines = vec![format!(
+            "Fetching articles [{}{}] {}/{}, {} failed, {:.1} s",
+            "#".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            self.done,
+            self.total,
+            self.failed.len(),
+            self.started.elapsed().as_secs_f64()
+        )];
+
+        for link in self.in_flight.iter().take(MAX_IN_FLIGHT_LINES) {
+            lines.push(truncate(&format!("  {}", link)));
+        }
+        if self.in_flight.len() > MAX_IN_FLIGHT_LINES {
+            lines.push(format!(
+                "  ... and {} more",
+                self.in_flight.len() - MAX_IN_FLIGHT_LINES
+            ));
+        }
+        lines
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "Fetched {} of {} articles in {:.1} s ({} failed)",
+            self.done - self.failed.len(),
+            self.total,
+            self.started.elapsed().as_secs_f64(),
+            self.failed.len()
+        )
+    }
+}
+
+fn truncate(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_LENGTH {
+        line.to_string()
+    } else {
+        let mut line = line.chars().take(MAX_LINE_LENGTH - 3).collect::<String>();
+        line.push_str("...");
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_status_block() {
+        let status = Status {
+            total: 10,
+            done: 5,
+            failed: vec!["https://example.com/failed".to_string()],
+            in_flight: (0..7)
+                .map(|i| format!("https://example.com/{}{}", i, "x".repeat(100)))
+                .collect(),
+            started: Instant::now(),
+            slowest: None,
+            live: true,
+            drawn: 0,
+        };
+
+        let lines = status.lines();
+
+        assert!(lines[0]
+            .starts_with("Fetching articles [###############               ] 5/10, 1 failed"));
+        assert_eq!(lines.len(), 1 + MAX_IN_FLIGHT_LINES + 1);
+        assert!(lines[1].starts_with("  https://example.com/0xxx"));
+        assert!(lines[1].ends_with("...") && lines[1].chars().count() == MAX_LINE_LENGTH);
+        assert_eq!(lines[6], "  ... and 2 more");
+        assert!(status.summary().starts_with("Fetched 4 of 10 articles in "));
+    }
+}
@@ -0,0 +1,333 @@
+use kuchiki::traits::*;
+use kuchiki::{Node, NodeRef};
+use regex::Regex;
+use std::collections::HashMap;
+
+// A content extractor based on the scoring heuristics used by Mozilla's Readability. It is used
+// as a fallback when extrablatt can't find the article node.
+//
+// Each paragraph-like element adds a score to its parent and (half of it to) its grandparent
+// depending on how much text it contains. The scores are adjusted by the tag name and the
+// class/id of the candidates and penalized by their link density. The best candidate is then
+// merged with any siblings that also look like content.
+
+lazy_static! {
+    static ref UNLIKELY: Regex = Regex::new(
+        "(?i)-ad-|ai2html|banner|breadcrumbs|combx|comment|community|cover-wrap|disqus|extra|\
+        footer|gdpr|header|legends|menu|related|remark|replies|rss|shoutbox|sidebar|skyscraper|\
+        social|sponsor|supplemental|ad-break|agegate|pagination|pager|popup|yom-remote|newsletter"
+    )
+    .unwrap();
+    static ref MAYBE_CANDIDATE: Regex =
+        Regex::new("(?i)and|article|body|column|content|main|shadow").unwrap();
+    static ref POSITIVE: Regex = Regex::new(
+        "(?i)article|body|content|entry|hentry|h-entry|main|page|pagination|post|text|blog|story"
+    )
+    .unwrap();
+    static ref NEGATIVE: Regex = Regex::new(
+        "(?i)-ad-|hidden|^hid$| hid$| hid |^hid |banner|combx|comment|com-|contact|foot|footer|\
+        footnote|gdpr|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|\
+        skyscraper|sponsor|shopping|tags|tool|widget|newsletter"
+    )
+    .unwrap();
+}
+
+/// Elements that are scored as paragraphs.
+const SCORED_TAGS: &[&str] = &["p", "td", "pre", "section", "h2", "h3", "h4", "h5", "h6"];
+
+/// Elements that never contain article content.
+const REMOVED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "form", "button", "iframe", "aside", "select",
+    "textarea", "input",
+];
+
+/// Paragraphs shorter than this don't contribute to the score of their ancestors.
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+/// Finds the node most likely to contain the article content in the HTML document. The returned
+/// node is a new document containing a copy of the content.
+pub fn article_node(doc: &NodeRef) -> Option<NodeRef> {
+    // Work on a copy of the document since nodes are removed while scoring.
+    let doc = kuchiki::parse_html().one(doc.to_string());
+    remove_unlikely_candidates(&doc);
+
+    let scores = score_candidates(&doc);
+    let (top, top_score) = scores
+        .values()
+        .map(|(node, score)| (node.clone(), score * (1.0 - link_density(node))))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))?;
+
+    let content = merge_siblings(&top, top_score, &scores);
+    Some(kuchiki::parse_html().one(content))
+}
+
+fn remove_unlikely_candidates(doc: &NodeRef) {
+    let mut targets = Vec::new();
+
+    for node in doc.descendants() {
+        let elem = match node.as_element() {
+            Some(elem) => elem,
+            None => continue,
+        };
+        let tag = &*elem.name.local;
+        if REMOVED_TAGS.contains(&tag) {
+            targets.push(node.clone());
+            continue;
+        }
+        if ["html", "body", "article", "main", "a"].contains(&tag) {
+            continue;
+        }
+        let attrs = elem.attributes.borrow();
+        let class_id = class_and_id(&attrs);
+        let hidden = attrs.get("hidden").is_some() || attrs.get("aria-hidden") == Some("true");
+        if hidden || (UNLIKELY.is_match(&class_id) && !MAYBE_CANDIDATE.is_match(&class_id)) {
+            targets.push(node.clone());
+        }
+    }
+
+    for target in targets {
+        target.detach();
+    }
+}
+
+type Scores = HashMap<*const Node, (NodeRef, f64)>;
+
+fn score_candidates(doc: &NodeRef) -> Scores {
+    let mut scores = Scores::new();
+
+    let paragraphs = doc
+        .descendants()
+        .filter(|node| {
+            node.as_element()
+                .is_some_and(|elem| SCORED_TAGS.contains(&&*elem.name.local))
+        })
+        .collect::<Vec<_>>();
+
+    for paragraph in paragraphs {
+        let text = normalized_text(&paragraph);
+        if text.len() < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+
+        for (level, ancestor) in paragraph.ancestors().take(3).enumerate() {
+            if ancestor.as_element().is_none() {
+                break;
+            }
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                _ => 6.0,
+            };
+            let entry = scores
+                .entry(key(&ancestor))
+                .or_insert_with(|| (ancestor.clone(), initial_score(&ancestor)));
+            entry.1 += score / divider;
+        }
+    }
+
+    scores
+}
+
+/// The score a candidate starts with, based on its tag name and class/id.
+fn initial_score(node: &NodeRef) -> f64 {
+    let elem = match node.as_element() {
+        Some(elem) => elem,
+        None => return 0.0,
+    };
+
+    let tag_score = match &*elem.name.local {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    tag_score + class_weight(node)
+}
+
+/// Returns a positive weight if the class or id of the node suggests content and a negative
+/// weight if it suggests something else, like comments or ads.
+fn class_weight(node: &NodeRef) -> f64 {
+    let elem = match node.as_element() {
+        Some(elem) => elem,
+        None => return 0.0,
+    };
+    let attrs = elem.attributes.borrow();
+
+    let mut weight = 0.0;
+    for value in [attrs.get("class"), attrs.get("id")].iter().flatten() {
+        if NEGATIVE.is_match(value) {
+            weight -= 25.0;
+        }
+        if POSITIVE.is_match(value) {
+            weight += 25.0;
+        }
+    }
+    if attrs.get("role") == Some("main") || attrs.get("itemprop") == Some("articleBody") {
+        weight += 25.0;
+    }
+    weight
+}
+
+/// The amount of text inside links compared to all text in the node, between 0 and 1.
+fn link_density(node: &NodeRef) -> f64 {
+    let text_length = normalized_text(node).len();
+    if text_length == 0 {
+        return 0.0;
+    }
+    let link_length: usize = node
+        .select("a")
+        .map(|links| links.map(|a| normalized_text(a.as_node()).len()).sum())
+        .unwrap_or(0);
+
+    link_length as f64 / text_length as f64
+}
+
+/// Wraps the top candidate together with any siblings that look like they are part of the
+/// article content (articles are often split into several containers) and returns the HTML.
+fn merge_siblings(top: &NodeRef, top_score: f64, scores: &Scores) -> String {
+    let parent = match top.parent() {
+        Some(parent) if parent.as_element().is_some() => parent,
+        _ => return format!("<div>{}</div>", top.to_string()),
+    };
+
+    let threshold = (top_score * 0.2).max(10.0);
+    let top_class = class_attr(top);
+
+    let mut html = String::from("<div>");
+    for sibling in parent.children() {
+        if sibling == *top {
+            html.push_str(&top.to_string());
+            continue;
+        }
+        if sibling.as_element().is_none() {
+            continue;
+        }
+
+        let mut bonus = 0.0;
+        if top_class.is_some() && class_attr(&sibling) == top_class {
+            bonus += top_score * 0.2;
+        }
+
+        let include = match scores.get(&key(&sibling)) {
+            Some((_, score)) => score * (1.0 - link_density(&sibling)) + bonus >= threshold,
+            None => is_content_paragraph(&sibling),
+        };
+        if include {
+            html.push_str(&sibling.to_string());
+        }
+    }
+    html.push_str("</div>");
+    html
+}
+
+fn is_content_paragraph(node: &NodeRef) -> bool {
+    let is_p = node
+        .as_element()
+        .is_some_and(|elem| &*elem.name.local == "p");
+    if !is_p {
+        return false;
+    }
+
+    let text = normalized_text(node);
+    let density = link_density(node);
+    (text.len() > 80 && density < 0.25)
+        || (!text.is_empty() && density == 0.0 && (text.ends_with('.') || text.contains(". ")))
+}
+
+fn class_attr(node: &NodeRef) -> Option<String> {
+    node.as_element()
+        .and_then(|elem| elem.attributes.borrow().get("class").map(str::to_string))
+        .filter(|class| !class.is_empty())
+}
+
+fn class_and_id(attrs: &kuchiki::Attributes) -> String {
+    format!(
+        "{} {}",
+        attrs.get("class").unwrap_or_default(),
+        attrs.get("id").unwrap_or_default()
+    )
+}
+
+fn normalized_text(node: &NodeRef) -> String {
+    node.text_contents()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn key(node: &NodeRef) -> *const Node {
+    &**node as *const Node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAGRAPH: &str = "This is a paragraph of article text, which is long enough to be \
+        scored, and contains a few commas, like real article text does.";
+
+    #[test]
+    fn prefers_article_over_comments() {
+        let html = format!(
+            "<html><body>
+                <div class='comments'><p>{p}</p><p>{p}</p><p>{p}</p></div>
+                <article><div><p>{p}</p><p>{p}</p></div></article>
+            </body></html>",
+            p = PARAGRAPH
+        );
+
+        let node = article_node(&kuchiki::parse_html().one(html)).unwrap();
+
+        assert!(node.select_first(".comments").is_err());
+        assert_eq!(node.select("p").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn penalizes_link_heavy_candidates() {
+        let link = format!("<a href='/'>{}</a>", PARAGRAPH);
+        let html = format!(
+            "<html><body>
+                <div id='links'><p>{l}</p><p>{l}</p><p>{l}</p></div>
+                <div id='story'><p>{p}</p><p>{p}</p></div>
+            </body></html>",
+            l = link,
+            p = PARAGRAPH
+        );
+
+        let node = article_node(&kuchiki::parse_html().one(html)).unwrap();
+
+        assert!(node.select_first("#story").is_ok());
+        assert!(node.select_first("#links").is_err());
+    }
+
+    #[test]
+    fn merges_content_siblings() {
+        let html = format!(
+            "<html><body><div>
+                <div class='body' id='first'><p>{p}</p><p>{p}</p><p>{p}</p></div>
+                <div class='ad'>Buy now!</div>
+                <div class='body' id='second'><p>{p}</p></div>
+                <p>{p}</p>
+            </div></body></html>",
+            p = PARAGRAPH
+        );
+
+        let node = article_node(&kuchiki::parse_html().one(html)).unwrap();
+
+        assert!(node.select_first("#first").is_ok());
+        assert!(node.select_first("#second").is_ok());
+        assert!(node.select_first(".ad").is_err());
+        assert_eq!(node.select("p").unwrap().count(), 5);
+    }
+
+    #[test]
+    fn returns_none_without_content() {
+        let doc = kuchiki::parse_html().one("<html><body><div>short</div></body></html>");
+        assert!(article_node(&doc).is_none());
+    }
+}
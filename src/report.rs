@@ -0,0 +1,128 @@
+use crate::export::{DigestItem, FetchStatus};
+use crate::resolve::ResolvedItem;
+use crate::util;
+use anyhow::Result as AnyResult;
+use chrono::Local;
+use serde::Serialize;
+use std::path::Path;
+
+/// The result of fetching every link of a run.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// When the report was generated (RFC 3339).
+    pub generated: String,
+    pub ok: usize,
+    pub failed: usize,
+    pub links: Vec<LinkReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkReport {
+    /// The title of the feed item the link is in.
+    pub item: String,
+    pub url: String,
+    pub status: FetchStatus,
+    /// The extractor that found the article content, see `Article::extractor`.
+    pub extractor: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Report {
+    pub fn new(resolved: &[ResolvedItem]) -> Self {
+        let links = resolved
+            .iter()
+            .map(DigestItem::new)
+            .flat_map(|item| {
+                let title = item.title;
+                item.articles.into_iter().map(move |article| LinkReport {
+                    item: title.clone(),
+                    url: article.url,
+                    status: article.status,
+                    extractor: article.extractor,
+                    error: article.error,
+                })
+            })
+            .collect::<Vec<_>>();
+        let failed = links
+            .iter()
+            .filter(|link| link.status == FetchStatus::Error)
+            .count();
+
+        Self {
+            generated: Local::now().to_rfc3339(),
+            ok: links.len() - failed,
+            failed,
+            links,
+        }
+    }
+
+    /// Logs the status of every link.
+    pub fn log(&self) {
+        info!("Report: {} ok, {} failed", self.ok, self.failed);
+        for link in &self.links {
+            match link.status {
+                FetchStatus::Ok => info!(
+                    "  ok     {:18} {}",
+                    link.extractor.as_deref().unwrap_or_default(),
+                    link.url
+                ),
+                FetchStatus::Error => info!(
+                    "  error  {:18} {} - {}",
+                    "",
+                    link.url,
+                    link.error.as_deref().unwrap_or_default()
+                ),
+            }
+        }
+    }
+
+    /// Writes the report as JSON to the file (or stdout).
+    pub fn write(&self, path: &Path) -> AnyResult<()> {
+        util::write_file(path, &serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::Article;
+    use crate::feed::Item;
+    use crate::resolve::LinkError;
+
+    #[test]
+    fn reports_every_link_in_order() {
+        let links = vec![
+            "https://a.example.com/".to_string(),
+            "https://b.example.com/".to_string(),
+        ];
+        let resolved = ResolvedItem {
+            item: Item {
+                title: "Title".to_string(),
+                date: None,
+                description: "".to_string(),
+                links: links.clone(),
+                tags: vec![],
+            },
+            articles: vec![Article {
+                url: links[1].clone(),
+                extractor: "readability".to_string(),
+                ..Default::default()
+            }],
+            errors: vec![LinkError {
+                url: links[0].clone(),
+                error: "timeout".to_string(),
+            }],
+            actions: vec![],
+        };
+
+        let report = Report::new(&[resolved]);
+
+        assert_eq!((report.ok, report.failed), (1, 1));
+        assert_eq!(report.links[0].url, links[0]);
+        assert_eq!(report.links[0].status, FetchStatus::Error);
+        assert_eq!(report.links[0].error.as_deref(), Some("timeout"));
+        assert_eq!(report.links[1].extractor.as_deref(), Some("readability"));
+        assert_eq!(report.links[1].item, "Title");
+    }
+}
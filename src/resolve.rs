@@ -1,12 +1,15 @@
+use crate::action;
 use crate::extract::{Article, ArticleExtractor};
 use crate::feed::Item;
+use crate::progress;
 use anyhow::Result as AnyResult;
 use anyhow::*;
 use extrablatt::select::document::Document;
 use extrablatt::DefaultExtractor;
 use rayon::prelude::*;
+use std::io::Read;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use ureq::{Agent, AgentBuilder};
 use url::Url;
 
@@ -14,46 +17,119 @@ use url::Url;
 pub struct ResolvedItem {
     pub item: Item,
     pub articles: Vec<Article>,
+    /// The links that could not be fetched or extracted.
+    pub errors: Vec<LinkError>,
+    /// The call-to-action links, such as petitions. These are not fetched.
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct LinkError {
+    pub url: String,
+    pub error: String,
 }
 
 pub fn resolve_items(items: Vec<Item>) -> Vec<ResolvedItem> {
     let agent = AgentBuilder::new().timeout(Duration::from_secs(20)).build();
-    items
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let actions = action::action_links(&item);
+            (item, actions)
+        })
+        .collect::<Vec<_>>();
+    progress::start(
+        items
+            .iter()
+            .map(|(item, actions)| item.links.len() - actions.len())
+            .sum(),
+    );
+    let resolved = items
         .into_par_iter()
-        .map(|item: Item| {
-            let articles = fetch_articles(&agent, &item.links);
-            ResolvedItem { item, articles }
+        .map(|(item, actions): (Item, Vec<String>)| {
+            let links = item
+                .links
+                .iter()
+                .filter(|link| !actions.contains(link))
+                .cloned()
+                .collect::<Vec<_>>();
+            let (articles, errors) = fetch_articles(&agent, &links);
+            ResolvedItem {
+                item,
+                articles,
+                errors,
+                actions,
+            }
         })
-        .collect()
+        .collect();
+    progress::finish();
+    resolved
 }
 
-fn fetch_articles(agent: &Agent, links: &[String]) -> Vec<Article> {
-    links
-        .iter()
-        .filter_map(|link| {
-            let res = fetch_article(&agent, link);
-            match &res {
-                Err(err) => println!("{} ... Error: {} - skipping article", link, err),
-                Ok(_) => println!("{} ... Ok", link),
-            }
-            res.ok()
+/// Wraps the items without fetching their articles, for --no-fetch.
+pub fn unresolved_items(items: Vec<Item>) -> Vec<ResolvedItem> {
+    items
+        .into_iter()
+        .map(|item| ResolvedItem {
+            actions: action::action_links(&item),
+            item,
+            articles: Vec::new(),
+            errors: Vec::new(),
         })
         .collect()
 }
 
+fn fetch_articles(agent: &Agent, links: &[String]) -> (Vec<Article>, Vec<LinkError>) {
+    let mut articles = Vec::new();
+    let mut errors = Vec::new();
+
+    for link in links {
+        progress::link_started(link);
+        let start = Instant::now();
+        match fetch_article(agent, link) {
+            Ok(article) => {
+                progress::link_finished(link, start.elapsed(), None);
+                articles.push(article);
+            }
+            Err(err) => {
+                progress::link_finished(link, start.elapsed(), Some(&err.to_string()));
+                errors.push(LinkError {
+                    url: link.to_string(),
+                    error: format!("{:#}", err),
+                });
+            }
+        }
+    }
+    (articles, errors)
+}
+
 fn fetch_article(agent: &Agent, link: &str) -> AnyResult<Article> {
     // TODO: does this follow redirects?
+    let start = Instant::now();
     let resp = agent.get(link).call().context("failed to get article")?;
-    let doc = Document::from_read(resp.into_reader())?;
-    extract_article(doc, Url::from_str(link)?)
+    let mut body = Vec::new();
+    resp.into_reader()
+        .read_to_end(&mut body)
+        .context("failed to read article")?;
+    debug!(
+        "{} downloaded {} bytes in {} ms",
+        link,
+        body.len(),
+        start.elapsed().as_millis()
+    );
+
+    let start = Instant::now();
+    let doc = Document::from_read(body.as_slice())?;
+    let article = extract_article(doc, Url::from_str(link)?);
+    debug!("{} extracted in {} ms", link, start.elapsed().as_millis());
+    article
 }
 
 fn extract_article(doc: Document, url: Url) -> AnyResult<Article> {
     let article_extractor = ArticleExtractor {
-        extractor: DefaultExtractor::default(),
+        extractor: DefaultExtractor,
         url,
         doc,
-        print_warnings: true,
     };
 
     article_extractor.extract()
@@ -88,10 +164,18 @@ mod tests {
             .authors
             .contains(&"Julia Conley, staff writer".to_string()));
 
-        // TODO: fix date being None
+        let date = article.publishing_date.unwrap();
+        assert_eq!(date.raw, "2020-11-22T17:24:42-05:00");
+        assert_eq!(date.normalized().as_deref(), Some("2020-11-22T17:24:42"));
+        assert_eq!(date.to_string(), "November 22, 2020");
+        assert_eq!(article.publisher.as_deref(), Some("Common Dreams"));
+        assert_eq!(article.extractor, "commondreams.org");
         assert_eq!(
-            article.publishing_date.as_deref(),
-            None, //Some("Sunday, November 22, 2020")
+            article.lead_image.as_deref(),
+            Some(
+                "https://www.commondreams.org/sites/default/files/headline/thumbs/\
+                gettyimages-1229708776_1_1.jpg"
+            )
         );
     }
 
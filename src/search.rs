@@ -0,0 +1,255 @@
+use crate::archive::{Archive, ArchivedItem};
+use crate::export::DigestItem;
+use anyhow::Result as AnyResult;
+use kuchiki::traits::TendrilSink;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_VERSION: u32 = 1;
+const INDEX_FILE: &str = "index.json";
+
+/// An inverted index over the archived items. It is stored next to the archive and updated
+/// every time new items are archived.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    /// The number of terms in each indexed document, keyed by archive id.
+    documents: BTreeMap<String, usize>,
+    /// Maps each term to the documents it occurs in and how many times.
+    postings: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+#[derive(Debug)]
+pub struct SearchResult {
+    pub item: ArchivedItem,
+    pub score: u32,
+    /// A piece of text around the first match.
+    pub snippet: String,
+}
+
+impl Index {
+    fn new() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            documents: BTreeMap::new(),
+            postings: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the index or returns an empty index if it doesn't exist or can't be read.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Index>(&json).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_else(Index::new)
+    }
+
+    pub fn save(&self, path: &Path) -> AnyResult<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Adds the document to the index, replacing it if it is already indexed.
+    pub fn add(&mut self, id: &str, text: &str) {
+        self.remove(id);
+
+        let mut count = 0;
+        for (_, term) in terms(text) {
+            *self
+                .postings
+                .entry(term)
+                .or_default()
+                .entry(id.to_string())
+                .or_default() += 1;
+            count += 1;
+        }
+        self.documents.insert(id.to_string(), count);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if self.documents.remove(id).is_some() {
+            self.postings.retain(|_, docs| {
+                docs.remove(id);
+                !docs.is_empty()
+            });
+        }
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.documents.contains_key(id)
+    }
+
+    /// Returns the ids of the documents that contain all terms in the query together with the
+    /// total number of occurrences of the terms, best match first.
+    pub fn search(&self, query: &str) -> Vec<(String, u32)> {
+        let mut scores: Option<BTreeMap<String, u32>> = None;
+
+        for (_, term) in terms(query) {
+            let docs = match self.postings.get(&term) {
+                Some(docs) => docs,
+                None => return Vec::new(),
+            };
+            scores = Some(match scores {
+                None => docs.clone(),
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| docs.get(&id).map(|n| (id, score + n)))
+                    .collect(),
+            });
+        }
+
+        let mut ret = scores.unwrap_or_default().into_iter().collect::<Vec<_>>();
+        // Ids start with the item date so newer items are preferred on equal scores.
+        ret.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+        ret
+    }
+}
+
+fn index_path(archive: &Archive) -> PathBuf {
+    archive.dir().join(INDEX_FILE)
+}
+
+/// Indexes the given archive items as well as any archived items missing from the index, and
+/// returns the updated index.
+pub fn update_index(archive: &Archive, ids: &[String]) -> AnyResult<Index> {
+    let path = index_path(archive);
+    let mut index = Index::load(&path);
+
+    let outdated = archive
+        .ids()?
+        .into_iter()
+        .filter(|id| !index.contains(id) || ids.contains(id))
+        .collect::<Vec<_>>();
+
+    if !outdated.is_empty() {
+        for id in outdated {
+            let archived = archive.get(&id)?;
+            index.add(&id, &document_text(&archived.item));
+        }
+        index.save(&path)?;
+    }
+
+    Ok(index)
+}
+
+/// Searches the archive and returns at most `limit` matching items.
+pub fn search(archive: &Archive, query: &str, limit: usize) -> AnyResult<Vec<SearchResult>> {
+    let index = update_index(archive, &[])?;
+
+    let query_terms = terms(query).map(|t| t.1).collect::<Vec<_>>();
+
+    index
+        .search(query)
+        .into_iter()
+        .take(limit)
+        .map(|(id, score)| {
+            let item = archive.get(&id)?;
+            let snippet = snippet(&document_text(&item.item), &query_terms);
+            Ok(SearchResult {
+                item,
+                score,
+                snippet,
+            })
+        })
+        .collect()
+}
+
+/// The searchable text of an item: its title, RMS's comment and the articles.
+fn document_text(item: &DigestItem) -> String {
+    let description = kuchiki::parse_html()
+        .one(item.description.as_str())
+        .text_contents();
+
+    let mut parts = vec![item.title.clone(), description];
+    for article in &item.articles {
+        parts.extend(article.title.clone());
+        parts.extend(article.text.clone());
+    }
+    parts.join("\n\n")
+}
+
+/// Splits the text into lowercase alphanumeric terms and returns each term together with its
+/// byte offset in the text.
+fn terms(text: &str) -> impl Iterator<Item = (usize, String)> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| {
+            let offset = word.as_ptr() as usize - text.as_ptr() as usize;
+            (offset, word.to_lowercase())
+        })
+}
+
+fn snippet(text: &str, query_terms: &[String]) -> String {
+    const BEFORE: usize = 60;
+    const AFTER: usize = 140;
+
+    let offset = terms(text)
+        .find(|(_, term)| query_terms.contains(term))
+        .map_or(0, |(offset, _)| offset);
+
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .nth(BEFORE - 1)
+        .map_or(0, |(i, _)| i);
+    let end = text[offset..]
+        .char_indices()
+        .nth(AFTER)
+        .map_or(text.len(), |(i, _)| offset + i);
+
+    let mut snippet = text[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < text.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_documents_containing_all_terms() {
+        let mut index = Index::new();
+        index.add("20201120-a", "Patents on vaccines. More patents!");
+        index.add("20201121-b", "Vaccine patents");
+        index.add("20201122-c", "Surveillance");
+
+        assert_eq!(
+            index.search("PATENTS"),
+            vec![("20201120-a".to_string(), 2), ("20201121-b".to_string(), 1)]
+        );
+        assert_eq!(
+            index.search("vaccine patents"),
+            vec![("20201121-b".to_string(), 2)]
+        );
+        assert!(index.search("patents surveillance").is_empty());
+
+        index.add("20201122-c", "Surveillance patents");
+        index.remove("20201120-a");
+        assert_eq!(
+            index.search("patents"),
+            vec![("20201122-c".to_string(), 1), ("20201121-b".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn creates_snippet_around_first_match() {
+        let text = format!("{} needle {}", "a ".repeat(100), "b ".repeat(100));
+        let snippet = snippet(&text, &["needle".to_string()]);
+
+        assert!(snippet.starts_with("...a a"));
+        assert!(snippet.contains("needle"));
+        assert!(snippet.ends_with("b b..."));
+        assert_eq!(snippet, snippet.trim());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::feed::Item;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+// Items are tagged with topics by looking for keywords in the title and description. The keyword
+// dictionary is a JSON list of tags and their keywords, see resources/tags.json for the built-in
+// dictionary.
+
+const DEFAULT_DICTIONARY: &str = include_str!("../resources/tags.json");
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    tag: String,
+    keywords: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Tagger {
+    /// The tags in dictionary order, with a regex matching any of the tag's keywords.
+    tags: Vec<(String, Regex)>,
+}
+
+impl Tagger {
+    /// Loads the dictionary file, or the built-in dictionary if no file is given.
+    pub fn load(path: Option<&Path>) -> AnyResult<Tagger> {
+        match path {
+            Some(path) => {
+                let dictionary = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read tag dictionary {}", path.display()))?;
+                Tagger::parse(&dictionary)
+                    .with_context(|| format!("invalid tag dictionary {}", path.display()))
+            }
+            None => Tagger::parse(DEFAULT_DICTIONARY),
+        }
+    }
+
+    fn parse(dictionary: &str) -> AnyResult<Tagger> {
+        let entries: Vec<Entry> = serde_json::from_str(dictionary)?;
+        let tags = entries
+            .into_iter()
+            .filter(|entry| !entry.keywords.is_empty())
+            .map(|entry| {
+                let keywords = entry
+                    .keywords
+                    .iter()
+                    .map(|keyword| keyword_regex(keyword))
+                    .collect::<Vec<_>>();
+                let regex = RegexBuilder::new(&keywords.join("|"))
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("invalid keywords for tag `{}`", entry.tag))?;
+                Ok((entry.tag, regex))
+            })
+            .collect::<AnyResult<_>>()?;
+        Ok(Tagger { tags })
+    }
+
+    /// The names of all tags in dictionary order.
+    pub fn names(&self) -> Vec<String> {
+        self.tags.iter().map(|(tag, _)| tag.clone()).collect()
+    }
+
+    /// Returns the tags of the item in dictionary order.
+    pub fn tags(&self, item: &Item) -> Vec<String> {
+        let text = item.text();
+        self.tags
+            .iter()
+            .filter(|(_, regex)| regex.is_match(&text))
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+}
+
+impl Default for Tagger {
+    fn default() -> Self {
+        Tagger::parse(DEFAULT_DICTIONARY).expect("invalid built-in tag dictionary")
+    }
+}
+
+/// Keywords match whole words. A keyword ending in `*` also matches words that start with it.
+fn keyword_regex(keyword: &str) -> String {
+    let (keyword, prefix) = match keyword.strip_suffix('*') {
+        Some(keyword) => (keyword, true),
+        None => (keyword, false),
+    };
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+
+    let mut regex = regex::escape(keyword);
+    if is_word_char(keyword.chars().next()) {
+        regex.insert_str(0, r"\b");
+    }
+    if prefix {
+        regex.push_str(r"\w*");
+    } else if is_word_char(keyword.chars().last()) {
+        regex.push_str(r"\b");
+    }
+    regex
+}
+
+/// The id of the tag's section in the HTML output.
+pub fn anchor(tag: &str) -> String {
+    let slug = tag
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    format!("tag-{}", slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, description: &str) -> Item {
+        Item {
+            title: title.to_string(),
+            date: None,
+            description: description.to_string(),
+            links: vec![],
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn tags_items_by_keywords() {
+        let tagger = Tagger::default();
+
+        assert_eq!(
+            tagger.tags(&item(
+                "Urgent: Vaccine patents",
+                "<a href=\"https://example.com\">Patented vaccines</a> should be shared."
+            )),
+            ["urgent", "Covid", "patents"]
+        );
+        // Whole words only.
+        assert!(tagger.tags(&item("Warning", "Spring warmth")).is_empty());
+        assert_eq!(
+            tagger.tags(&item("Spying cops", "")),
+            ["surveillance", "police"]
+        );
+        assert_eq!(anchor("free software"), "tag-free-software");
+    }
+
+    #[test]
+    fn loads_dictionary() {
+        let tagger =
+            Tagger::parse(r#"[{ "tag": "cars", "keywords": ["car", "automobile*"] }]"#).unwrap();
+
+        assert_eq!(tagger.names(), ["cars"]);
+        assert_eq!(tagger.tags(&item("Automobiles and cars", "")), ["cars"]);
+        assert!(tagger.tags(&item("Carpets", "")).is_empty());
+        assert!(Tagger::parse(r#"{ "cars": ["car"] }"#).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+use crate::filter;
+use anyhow::Result as AnyResult;
+use anyhow::*;
+use std::collections::HashMap;
+
+// A minimal Handlebars style HTML template engine where values are escaped according to where
+// they appear in the template.
+//
+// Variables are written as {{name}}. Plain text values are escaped as text in element content, as
+// attribute values inside quoted attributes and additionally checked for safe URL schemes in URL
+// attributes (href, src...). Variables must not be used inside tags except as quoted attribute
+// values. Trusted HTML values (e.g. sanitized article HTML) are inserted as they are in element
+// content.
+//
+// Sections are written as {{#each list}}...{{/each}}, {{#if name}}...{{else}}...{{/if}} and
+// {{#unless name}}...{{/unless}}. Inside #each the values of the current list element are
+// available, as well as the values outside the list and @index, @first and @last.
+
+/// Attributes whose values are URLs.
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite", "action", "poster"];
+
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Var {
+        name: String,
+        escape: Escape,
+    },
+    Each {
+        name: String,
+        body: Vec<Part>,
+    },
+    If {
+        name: String,
+        negate: bool,
+        then: Vec<Part>,
+        otherwise: Vec<Part>,
+    },
+}
+
+/// How a variable is escaped, depending on where in the HTML it appears.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Escape {
+    Text,
+    Attribute,
+    Url,
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// Text that is escaped before it is inserted.
+    Text(String),
+    /// Trusted HTML that is inserted as is in element content.
+    Html(String),
+    Bool(bool),
+    List(Vec<Values>),
+}
+
+impl Value {
+    /// Empty strings and lists and false are falsy, everything else is truthy.
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(s) | Value::Html(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+            Value::List(list) => !list.is_empty(),
+        }
+    }
+}
+
+/// The values that a template is rendered with.
+#[derive(Debug, Default, Clone)]
+pub struct Values {
+    values: HashMap<String, Value>,
+}
+
+impl Values {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, name: &str, text: impl Into<String>) -> Self {
+        self.values
+            .insert(name.to_string(), Value::Text(text.into()));
+        self
+    }
+
+    pub fn html(mut self, name: &str, html: impl Into<String>) -> Self {
+        self.values
+            .insert(name.to_string(), Value::Html(html.into()));
+        self
+    }
+
+    pub fn bool(mut self, name: &str, value: bool) -> Self {
+        self.values.insert(name.to_string(), Value::Bool(value));
+        self
+    }
+
+    pub fn list(mut self, name: &str, list: Vec<Values>) -> Self {
+        self.values.insert(name.to_string(), Value::List(list));
+        self
+    }
+}
+
+/// The section a part of the template is in while parsing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Section {
+    Root,
+    Each,
+    If,
+    Unless,
+}
+
+impl Section {
+    fn name(self) -> &'static str {
+        match self {
+            Section::Root => "",
+            Section::Each => "each",
+            Section::If => "if",
+            Section::Unless => "unless",
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(template: &str) -> AnyResult<Template> {
+        let mut rest = template;
+        let mut scanner = Scanner::default();
+        let (parts, _) = parse_section(&mut rest, &mut scanner, Section::Root)?;
+        Ok(Template { parts })
+    }
+
+    /// Renders the template. Missing values are rendered as empty strings.
+    pub fn render(&self, values: &Values) -> String {
+        let mut out = String::new();
+        render_parts(&mut out, &self.parts, &[values]);
+        out
+    }
+}
+
+/// Parses the template until the end of the section and returns the parts of the section and
+/// the parts of its else branch.
+fn parse_section(
+    rest: &mut &str,
+    scanner: &mut Scanner,
+    section: Section,
+) -> AnyResult<(Vec<Part>, Vec<Part>)> {
+    let mut parts = Vec::new();
+    let mut otherwise = Vec::new();
+    let mut in_else = false;
+    // Sections must end in the same HTML context as they start in (e.g. not open a tag that
+    // is closed after the section), otherwise the context of the template text after the
+    // section would depend on the values.
+    let start = scanner.clone();
+    let check_context = |scanner: &Scanner| {
+        if !scanner.same_context(&start) {
+            bail!(
+                "{{{{#{}}}}} section must not start or end inside a tag",
+                section.name()
+            );
+        }
+        Ok(())
+    };
+
+    loop {
+        let start = match rest.find("{{") {
+            Some(start) => start,
+            None if section == Section::Root => {
+                parts.push(Part::Literal(rest.to_string()));
+                *rest = "";
+                return Ok((parts, otherwise));
+            }
+            None => bail!("unclosed {{{{#{}}}}} in template", section.name()),
+        };
+
+        let literal = &rest[..start];
+        scanner.scan(literal);
+        let current = if in_else { &mut otherwise } else { &mut parts };
+        current.push(Part::Literal(literal.to_string()));
+
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| anyhow!("unclosed {{{{ in template"))?;
+        let tag = rest[start + 2..start + end].trim().to_string();
+        *rest = &rest[start + end + 2..];
+
+        let mut words = tag.split_whitespace();
+        match (words.next().unwrap_or_default(), words.next(), words.next()) {
+            ("#each", Some(name), None) => {
+                let (body, _) = parse_section(rest, scanner, Section::Each)?;
+                current.push(Part::Each {
+                    name: variable_name(name)?,
+                    body,
+                });
+            }
+            (open @ "#if", Some(name), None) | (open @ "#unless", Some(name), None) => {
+                let negate = open == "#unless";
+                let nested = if negate { Section::Unless } else { Section::If };
+                let (then, otherwise) = parse_section(rest, scanner, nested)?;
+                current.push(Part::If {
+                    name: variable_name(name)?,
+                    negate,
+                    then,
+                    otherwise,
+                });
+            }
+            ("else", None, None) => {
+                if in_else || ![Section::If, Section::Unless].contains(&section) {
+                    bail!("unexpected {{{{else}}}} in template");
+                }
+                check_context(scanner)?;
+                in_else = true;
+            }
+            (close, None, None) if close.starts_with('/') => {
+                if close[1..] != *section.name() || section == Section::Root {
+                    bail!("unexpected {{{{{}}}}} in template", close);
+                }
+                check_context(scanner)?;
+                return Ok((parts, otherwise));
+            }
+            (name, None, None) => {
+                let name = variable_name(name)?;
+                let escape = scanner
+                    .escape()
+                    .with_context(|| format!("invalid use of template variable `{}`", name))?;
+                current.push(Part::Var { name, escape });
+            }
+            _ => bail!("invalid template tag `{}`", tag),
+        }
+    }
+}
+
+fn variable_name(name: &str) -> AnyResult<String> {
+    let valid = name.strip_prefix('@').unwrap_or(name);
+    if valid.is_empty() || !valid.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        bail!("invalid template variable `{}`", name);
+    }
+    Ok(name.to_string())
+}
+
+fn render_parts(out: &mut String, parts: &[Part], scopes: &[&Values]) {
+    let lookup = |name: &str| scopes.iter().rev().find_map(|scope| scope.values.get(name));
+
+    for part in parts {
+        match part {
+            Part::Literal(literal) => out.push_str(literal),
+            Part::Var { name, escape } => {
+                if let Some(value) = lookup(name) {
+                    render_value(out, value, *escape);
+                }
+            }
+            Part::Each { name, body } => {
+                if let Some(Value::List(list)) = lookup(name) {
+                    for (i, values) in list.iter().enumerate() {
+                        let meta = Values::new()
+                            .text("@index", i.to_string())
+                            .bool("@first", i == 0)
+                            .bool("@last", i + 1 == list.len());
+                        let mut nested = scopes.to_vec();
+                        nested.push(&meta);
+                        nested.push(values);
+                        render_parts(out, body, &nested);
+                    }
+                }
+            }
+            Part::If {
+                name,
+                negate,
+                then,
+                otherwise,
+            } => {
+                let truthy = lookup(name).is_some_and(Value::is_truthy);
+                let branch = if truthy != *negate { then } else { otherwise };
+                render_parts(out, branch, scopes);
+            }
+        }
+    }
+}
+
+fn render_value(out: &mut String, value: &Value, escape: Escape) {
+    match (value, escape) {
+        (Value::Bool(_), _) | (Value::List(_), _) => {}
+        (Value::Html(html), Escape::Text) => out.push_str(html),
+        (Value::Text(text), Escape::Text) => out.push_str(&escape_text(text)),
+        (Value::Text(s), Escape::Attribute) | (Value::Html(s), Escape::Attribute) => {
+            out.push_str(&escape_attribute(s))
+        }
+        (Value::Text(url), Escape::Url) | (Value::Html(url), Escape::Url) => {
+            if filter::is_safe_url(url) {
+                out.push_str(&escape_attribute(url))
+            } else {
+                out.push('#')
+            }
+        }
+    }
+}
+
+pub fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Keeps track of whether the template text scanned so far ends in element content, inside a
+/// tag or inside an attribute value.
+#[derive(Debug, Default, Clone)]
+struct Scanner {
+    state: State,
+    attribute: String,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+enum State {
+    #[default]
+    Text,
+    Tag,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValue(char),
+    UnquotedAttributeValue,
+}
+
+impl Scanner {
+    fn scan(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            self.state = match (self.state, c) {
+                (State::Text, '<') => match chars.peek() {
+                    Some(next) if next.is_ascii_alphabetic() || *next == '/' => State::Tag,
+                    _ => State::Text,
+                },
+                (State::Text, _) => State::Text,
+                (State::AttributeValue(quote), c) if c == quote => State::Tag,
+                (State::AttributeValue(quote), _) => State::AttributeValue(quote),
+                (_, '>') => State::Text,
+                (State::BeforeAttributeValue, '"') | (State::BeforeAttributeValue, '\'') => {
+                    State::AttributeValue(c)
+                }
+                (State::BeforeAttributeValue, c) if c.is_whitespace() => {
+                    State::BeforeAttributeValue
+                }
+                (State::BeforeAttributeValue, _) => State::UnquotedAttributeValue,
+                (State::UnquotedAttributeValue, c) if c.is_whitespace() => State::Tag,
+                (State::UnquotedAttributeValue, _) => State::UnquotedAttributeValue,
+                (State::AttributeName, '=') | (State::AfterAttributeName, '=') => {
+                    State::BeforeAttributeValue
+                }
+                (State::AttributeName, c) if c.is_whitespace() => State::AfterAttributeName,
+                (State::AttributeName, c) => {
+                    self.attribute.push(c.to_ascii_lowercase());
+                    State::AttributeName
+                }
+                (State::Tag, c) | (State::AfterAttributeName, c) if c.is_whitespace() => State::Tag,
+                (State::Tag, '/') => State::Tag,
+                (State::Tag, c) | (State::AfterAttributeName, c) => {
+                    // The first word in the tag is the tag name, treating it as an attribute
+                    // name is harmless.
+                    self.attribute.clear();
+                    self.attribute.push(c.to_ascii_lowercase());
+                    State::AttributeName
+                }
+            };
+        }
+    }
+
+    fn same_context(&self, other: &Scanner) -> bool {
+        self.state == other.state
+            && (self.state == State::Text || self.attribute == other.attribute)
+    }
+
+    fn escape(&self) -> AnyResult<Escape> {
+        match self.state {
+            State::Text => Ok(Escape::Text),
+            State::AttributeValue(_) if URL_ATTRIBUTES.contains(&self.attribute.as_str()) => {
+                Ok(Escape::Url)
+            }
+            State::AttributeValue(_) => Ok(Escape::Attribute),
+            _ => bail!("variables in tags must be inside quoted attribute values"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_by_context() {
+        let template = Template::parse(
+            r#"<h1 title="{{title}}">{{title}}</h1><a class='x' href="{{link}}">{{link}}</a>"#,
+        )
+        .unwrap();
+
+        let values = Values::new()
+            .text("title", r#"<b>"Tom" & 'Jerry'</b>"#)
+            .text("link", "https://example.com/?a=1&b=\"2\"");
+
+        assert_eq!(
+            template.render(&values),
+            "<h1 title=\"&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;\">\
+            &lt;b&gt;\"Tom\" &amp; 'Jerry'&lt;/b&gt;</h1>\
+            <a class='x' href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">\
+            https://example.com/?a=1&amp;b=\"2\"</a>"
+        );
+    }
+
+    #[test]
+    fn inserts_html_values_in_text_only() {
+        let template = Template::parse(r#"<div title="{{body}}">{{body}}</div>"#).unwrap();
+        let values = Values::new().html("body", "<p>x</p>");
+
+        assert_eq!(
+            template.render(&values),
+            r#"<div title="&lt;p&gt;x&lt;/p&gt;"><p>x</p></div>"#
+        );
+    }
+
+    #[test]
+    fn rejects_unsafe_urls() {
+        let template = Template::parse(r#"<a href="{{link}}">x</a><img src='{{link}}'>"#).unwrap();
+        let values = Values::new().text("link", "javascript:alert(1)");
+
+        assert_eq!(
+            template.render(&values),
+            r##"<a href="#">x</a><img src='#'>"##
+        );
+    }
+
+    #[test]
+    fn rejects_variables_outside_attribute_values() {
+        assert!(Template::parse("<a {{attr}}>").is_err());
+        assert!(Template::parse("<a href={{link}}>").is_err());
+        assert!(Template::parse("{{unclosed").is_err());
+        assert!(Template::parse("{{not valid}}").is_err());
+        assert!(Template::parse("a < b {{x}} <br/> {{y}}").is_ok());
+    }
+
+    #[test]
+    fn renders_sections() {
+        let template = Template::parse(
+            "{{#each items}}{{#unless @first}}, {{/unless}}{{name}}{{#if tag}} ({{tag}}){{else}}\
+            {{#if default}} [{{default}}]{{/if}}{{/if}}{{/each}}{{#each none}}x{{/each}}",
+        )
+        .unwrap();
+
+        let values = Values::new()
+            .text("default", "d")
+            .list(
+                "items",
+                vec![
+                    Values::new().text("name", "a").text("tag", "<t>"),
+                    Values::new().text("name", "b"),
+                    Values::new().text("name", "c").text("default", ""),
+                ],
+            )
+            .list("none", vec![]);
+
+        assert_eq!(template.render(&values), "a (&lt;t&gt;), b [d], c");
+    }
+
+    #[test]
+    fn rejects_invalid_sections() {
+        assert!(Template::parse("{{#each items}}").is_err());
+        assert!(Template::parse("{{#if a}}{{/each}}").is_err());
+        assert!(Template::parse("{{/if}}").is_err());
+        assert!(Template::parse("{{else}}").is_err());
+        assert!(Template::parse("{{#each a}}{{else}}{{/each}}").is_err());
+        assert!(Template::parse("{{#if a b}}{{/if}}").is_err());
+        assert!(Template::parse("{{#if a}}{{else}}{{else}}{{/if}}").is_err());
+        assert!(Template::parse("{{#if a}}<a {{/if}}href=\"{{x}}\">").is_err());
+        assert!(Template::parse("<p class=\"{{#if a}}x\">{{/if}}").is_err());
+        assert!(Template::parse("<p class=\"{{#if a}}x{{else}}y{{/if}}\">").is_ok());
+    }
+
+    #[test]
+    fn renders_missing_values_as_empty() {
+        let template = Template::parse("a{{missing}}b").unwrap();
+        assert_eq!(template.render(&Values::new()), "ab");
+    }
+}
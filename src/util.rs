@@ -1,12 +1,100 @@
 use anyhow::Result as AnyResult;
 use anyhow::*;
 use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use extrablatt::date::Date;
 use regex::Regex;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// The output file name that means stdout.
+pub const STDOUT: &str = "-";
+
+/// The file name prefix of the temp files the output is written to for the browser.
+const TEMP_FILE_PREFIX: &str = "the-daily-stallman-";
+
+/// The fixed temp file name used by earlier versions.
+const OLD_TEMP_FILE: &str = "123679816239the-daily-stallman.html";
 
 pub fn is_http(s: &str) -> bool {
     s.starts_with("http://") || s.starts_with("https://")
 }
 
+/// Returns a new temp file path for output that is opened in the browser. The name contains the
+/// time and process id so that runs don't overwrite a file that a browser is still loading.
+pub fn temp_file(extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "{}{}-{}.{}",
+        TEMP_FILE_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id(),
+        extension
+    ))
+}
+
+/// Removes temp files written by earlier runs that are older than `max_age`.
+pub fn remove_stale_temp_files(max_age: Duration) {
+    remove_stale_files(&std::env::temp_dir(), max_age, SystemTime::now());
+}
+
+fn remove_stale_files(dir: &Path, max_age: Duration, now: SystemTime) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with(TEMP_FILE_PREFIX) && name != OLD_TEMP_FILE {
+            continue;
+        }
+        let modified = entry.metadata().and_then(|m| m.modified());
+        let stale = modified
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= max_age);
+        if stale {
+            debug!("removing old temp file {}", entry.path().display());
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Returns the directory where persistent data such as the article archive is stored.
+/// This is the platform data directory unless overridden by the TDS_DATA_DIR environment variable.
+pub fn data_dir() -> AnyResult<PathBuf> {
+    if let Some(dir) = std::env::var_os("TDS_DATA_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    dirs::data_dir()
+        .map(|dir| dir.join("the-daily-stallman"))
+        .ok_or_else(|| anyhow!("could not find a data directory, set TDS_DATA_DIR"))
+}
+
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT)
+}
+
+/// Writes the contents to the file, or to stdout if the path is `-`.
+pub fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    if is_stdout(path) {
+        let mut stdout = io::stdout();
+        stdout.write_all(contents.as_bytes())?;
+        stdout.flush()
+    } else {
+        fs::write(path, contents)
+    }
+}
+
+/// A 64 bit FNV-1a hash. Unlike `DefaultHasher` the result is stable across Rust versions so it
+/// can be used for file names etc.
+pub fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Accepts dates in RFC 3339 (e.g. 2019-01-01T00:00:00Z), without hh:mm:Z (e.g. 2019-01-01T12),
 /// without time (e.g. 2019-01-01), and without day (e.g. 2019-01).
 /// All formats are parsed as UTC (except RFC 3339 which can specify a time zone).
@@ -35,10 +123,117 @@ pub fn parse_date_time(input: &str) -> AnyResult<NaiveDateTime> {
     }
 }
 
+/// Parses a publishing date as found in article metadata. Accepts RFC 3339, RFC 2822, dates
+/// written out in English (e.g. November 22, 2020) and the formats accepted by `parse_date_time`.
+/// Dates with a time zone are kept in that time zone.
+pub fn parse_publishing_date(input: &str) -> Option<Date> {
+    const DATE_FORMATS: &[&str] = &[
+        "%B %d, %Y",
+        "%A, %B %d, %Y",
+        "%b %d, %Y",
+        "%a, %b %d, %Y",
+        "%d %B %Y",
+        "%d %b %Y",
+    ];
+
+    let input = input.trim();
+
+    if let Ok(date) =
+        DateTime::parse_from_rfc3339(input).or_else(|_| DateTime::parse_from_rfc2822(input))
+    {
+        return Some(Date::DateTime(date.naive_local()));
+    }
+
+    if let Some(date) = DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(input, format).ok())
+    {
+        return Some(Date::Date(date));
+    }
+
+    let date_time = parse_date_time(input).ok()?;
+    // Without a time component (e.g. 2020-11-22) this is a date rather than midnight.
+    if input.len() <= "2020-11-22".len() {
+        Some(Date::Date(date_time.date()))
+    } else {
+        Some(Date::DateTime(date_time))
+    }
+}
+
+/// Parses a duration such as 90s, 30m, 1h or 1h30m. A number without a unit is in minutes.
+pub fn parse_duration(input: &str) -> AnyResult<std::time::Duration> {
+    lazy_static! {
+        static ref PART: Regex = Regex::new(r"(\d+)([smhd]?)").unwrap();
+    }
+
+    let input = input.trim();
+    let mut seconds = 0u64;
+    let mut end = 0;
+    for cap in PART.captures_iter(input) {
+        let part = cap.get(0).unwrap();
+        // A number without a unit is only allowed on its own.
+        let unitless = cap[2].is_empty() && (part.start() != 0 || part.end() != input.len());
+        if part.start() != end || unitless {
+            break;
+        }
+        end = part.end();
+        let unit = match &cap[2] {
+            "s" => 1,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => 60,
+        };
+        let value: u64 = cap[1].parse()?;
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|s| s.checked_add(seconds))
+            .ok_or_else(|| anyhow!("duration `{}` is too long", input))?;
+    }
+
+    if input.is_empty() || end != input.len() {
+        bail!("invalid duration `{}`, expected e.g. 90s, 30m or 1h", input);
+    }
+    Ok(std::time::Duration::from_secs(seconds))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn removes_stale_temp_files_only() {
+        let dir = std::env::temp_dir().join(format!("tds-util-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let temp_file = dir.join(format!("{}20201123-080000-1.html", TEMP_FILE_PREFIX));
+        let other_file = dir.join("other.html");
+        fs::write(&temp_file, "").unwrap();
+        fs::write(&other_file, "").unwrap();
+        let now = SystemTime::now() + Duration::from_secs(60);
+
+        remove_stale_files(&dir, Duration::from_secs(120), now);
+        assert!(temp_file.exists());
+
+        remove_stale_files(&dir, Duration::from_secs(30), now);
+        assert!(!temp_file.exists());
+        assert!(other_file.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let secs = |s| parse_duration(s).map(|d| d.as_secs()).ok();
+        assert_eq!(secs("90s"), Some(90));
+        assert_eq!(secs("30m"), Some(30 * 60));
+        assert_eq!(secs("30"), Some(30 * 60));
+        assert_eq!(secs("1h30m"), Some(90 * 60));
+        assert_eq!(secs("1d"), Some(24 * 60 * 60));
+        assert_eq!(secs(""), None);
+        assert_eq!(secs("m"), None);
+        assert_eq!(secs("30x"), None);
+        assert_eq!(secs("30 m"), None);
+        assert_eq!(secs("30m1"), None);
+    }
+
     #[test]
     fn test_parse_date_time() {
         assert_eq!(
@@ -61,6 +256,33 @@ mod tests {
         assert!(parse_date_time("2019-12-01T24:00:00").is_err());
     }
 
+    #[test]
+    fn test_parse_publishing_date() {
+        let parse = |s| match parse_publishing_date(s) {
+            Some(Date::Date(d)) => d.to_string(),
+            Some(Date::DateTime(d)) => d.to_string(),
+            None => "None".to_string(),
+        };
+
+        assert_eq!(parse("2020-11-22T23:24:42-05:00"), "2020-11-22 23:24:42");
+        assert_eq!(
+            parse("Sun, 22 Nov 2020 23:24:42 +0100"),
+            "2020-11-22 23:24:42"
+        );
+        assert_eq!(parse("Sunday, November 22, 2020"), "2020-11-22");
+        assert_eq!(parse("Nov 22, 2020"), "2020-11-22");
+        assert_eq!(parse(" 22 November 2020 "), "2020-11-22");
+        assert_eq!(parse("2020-11-22"), "2020-11-22");
+        assert_eq!(parse("2020-11-22T12"), "2020-11-22 12:00:00");
+        assert_eq!(parse("yesterday"), "None");
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        assert_eq!(stable_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
     fn date(d: &str) -> NaiveDateTime {
         chrono::DateTime::parse_from_rfc3339(d).unwrap().naive_utc()
     }
@@ -0,0 +1,62 @@
+use crate::archive::{self, Archive};
+use crate::feed::{self, Validators};
+use crate::options::Opts;
+use crate::resolve::{self, ResolvedItem};
+use crate::search;
+use anyhow::Result as AnyResult;
+use chrono::Local;
+use std::thread;
+use std::time::Duration;
+
+/// Polls the feed every `interval` and resolves the items that are not in the archive yet. The
+/// new items are archived and passed to `on_new_items`. Runs until an error occurs in
+/// `on_new_items`; errors while polling are logged and the feed is polled again later.
+pub fn watch(
+    interval: Duration,
+    opts: &Opts,
+    mut on_new_items: impl FnMut(Vec<ResolvedItem>) -> AnyResult<()>,
+) -> AnyResult<()> {
+    let archive = Archive::open()?;
+    let mut validators = Validators::default();
+
+    loop {
+        match poll(&archive, opts, &mut validators) {
+            Ok(new_items) if !new_items.is_empty() => on_new_items(new_items)?,
+            Ok(_) => {}
+            Err(err) => warn!("failed to poll the feed: {:#}", err),
+        }
+
+        let next = Local::now() + chrono::Duration::from_std(interval)?;
+        debug!("next poll at {}", next.format("%H:%M:%S"));
+        thread::sleep(interval);
+    }
+}
+
+fn poll(
+    archive: &Archive,
+    opts: &Opts,
+    validators: &mut Validators,
+) -> AnyResult<Vec<ResolvedItem>> {
+    // The feed server is asked to only send the feed if it has changed since the last poll.
+    let mut items = match feed::items_if_changed(validators)? {
+        Some(items) => items,
+        None => {
+            debug!("the feed has not changed");
+            return Ok(Vec::new());
+        }
+    };
+
+    // Items without a date can't be identified, so it is not known if they are new.
+    feed::filter_items(&mut items, opts);
+    items.retain(|item| archive::item_id(item).is_some_and(|id| !archive.contains(&id)));
+    if items.is_empty() {
+        debug!("no new items in the feed");
+        return Ok(Vec::new());
+    }
+
+    info!("{} new item(s) in the feed", items.len());
+    let resolved = resolve::resolve_items(items);
+    let ids = archive.store(&resolved)?;
+    search::update_index(archive, &ids)?;
+    Ok(resolved)
+}